@@ -36,6 +36,15 @@ in
       default = inputs.self.packages.${pkgs.stdenv.system}.rust-workspace;
     };
 
+    secretsBackend = lib.mkOption {
+      description = "backend used to keep the credentials of the agent at rest";
+      type = lib.types.enum [
+        "file"
+        "systemd-creds"
+      ];
+      default = "file";
+    };
+
     rust = {
       log = lib.mkOption {
         type = lib.types.str;
@@ -64,6 +73,12 @@ in
         default = "${cfg.nats.listenHost}:${builtins.toString cfg.nats.listenPort}";
       };
 
+      leafnodeClientCredsPath = lib.mkOption {
+        description = "path to the NATS credentials of the leafnode client connection. With the systemd-creds backend, this is the file encrypted by `host_agent host migrate-secret`";
+        type = lib.types.nullOr lib.types.str;
+        default = null;
+      };
+
       hub = {
         url = lib.mkOption {
           type = lib.types.str;
@@ -83,6 +98,13 @@ in
   };
 
   config = lib.mkIf cfg.enable {
+    # NB: `host_agent support bundle` is run from a shell, and encrypts the bundle with age.
+    # The bundle (like `host_agent host model-info`) also includes the hardware inventory, which reads the SMART health of the drives.
+    environment.systemPackages = [
+      pkgs.age
+      pkgs.smartmontools
+    ];
+
     systemd.services.holo-host-agent = {
       enable = true;
 
@@ -102,8 +124,26 @@ in
           NATS_URL = cfg.nats.url;
         };
 
+      serviceConfig =
+        {
+          # NB: The credentials that are not loaded by systemd are decrypted into this tmpfs, which is removed when the agent stops
+          RuntimeDirectory = "holo-host-agent";
+          RuntimeDirectoryMode = "0700";
+        }
+        // lib.attrsets.optionalAttrs
+          (cfg.secretsBackend == "systemd-creds" && cfg.nats.leafnodeClientCredsPath != null)
+          {
+            # NB: The credential name must match the file name, which the agent looks up in $CREDENTIALS_DIRECTORY
+            LoadCredentialEncrypted = "${builtins.baseNameOf cfg.nats.leafnodeClientCredsPath}:${cfg.nats.leafnodeClientCredsPath}";
+          };
+
       path = [
         pkgs.nats-server
+        # NB: Used to build support bundles
+        pkgs.age
+        pkgs.systemd
+        # NB: Used to read the SMART health of the drives for the hardware inventory
+        pkgs.smartmontools
       ];
 
       script =
@@ -127,6 +167,12 @@ in
           pkgs.writeShellScript "holo-host-agent" ''
             ${lib.getExe' cfg.package "host_agent"} daemonize \
               --hub-url=${cfg.nats.hub.url} \
+              --secrets-backend=${cfg.secretsBackend} \
+              ${
+                lib.optionalString (
+                  cfg.nats.leafnodeClientCredsPath != null
+                ) "--nats-leafnode-client-creds-path=${cfg.nats.leafnodeClientCredsPath}"
+              } \
               ${lib.optionalString cfg.nats.hub.tlsInsecure "--hub-tls-insecure"} \
               ${builtins.concatStringsSep " " extraDaemonizeArgsList}
           ''
@@ -91,8 +91,11 @@ craneLib.buildPackage (
             ]
             ++ (pkgs.lib.lists.optionals (!pkgs.stdenv.isAarch64) [
               # TODO: get mongodb built for aarch64
-              pkgs.mongodb
+              # NB: The bulk write helpers need MongoDB 8.0+, which is only packaged as mongodb-ce
+              pkgs.mongodb-ce
             ]);
+          # NB: This also runs the integration tests that inject faults into the orchestrator
+          cargoNextestExtraArgs = "--features workload/fault_injection";
           partitions = 1;
           partitionType = "count";
         }
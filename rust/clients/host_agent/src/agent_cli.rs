@@ -4,6 +4,8 @@ use std::path::PathBuf;
 /// command line. To start the agent daemon (usually from systemd), use `host_agent daemonize`.
 use clap::{Args, Parser, Subcommand};
 
+use crate::secrets::SecretsBackendKind;
+
 #[derive(Parser)]
 #[command(
     version,
@@ -43,6 +45,14 @@ pub struct DaemonzeArgs {
     )]
     pub(crate) nats_leafnode_client_creds_path: Option<PathBuf>,
 
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SecretsBackendKind::File,
+        help = "backend used to keep credentials at rest (eg: the NATS LeafNode client credentials)"
+    )]
+    pub(crate) secrets_backend: SecretsBackendKind,
+
     #[arg(long, help = "connection URL to the hub")]
     pub(crate) hub_url: String,
 
@@ -52,12 +62,31 @@ pub struct DaemonzeArgs {
     )]
     pub(crate) hub_tls_insecure: bool,
 
+    #[arg(
+        long,
+        help = "JetStream domain of the leaf server, which holds the workload traffic of this host"
+    )]
+    pub(crate) leaf_js_domain: Option<String>,
+
     #[arg(
         long,
         help = "try to connect to the (internally spawned) Nats instance for the given duration in seconds before giving up",
         default_value = "30"
     )]
     pub(crate) nats_connect_timeout_secs: u64,
+
+    #[arg(
+        long,
+        help = "path to the hardware watchdog device (eg: /dev/watchdog), which is only petted while the agent is healthy"
+    )]
+    pub(crate) watchdog_device: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "interval in seconds between two pets of the hardware watchdog. Must be lower than the watchdog timeout",
+        default_value = "10"
+    )]
+    pub(crate) watchdog_interval_secs: u64,
 }
 
 /// A set of commands for being able to manage the local host. We may (later) want to gate some
@@ -67,6 +96,25 @@ pub struct DaemonzeArgs {
 pub enum HostCommands {
     /// Display information about the current host model.
     ModelInfo,
+    /// Move a plaintext secret (eg: existing NATS credentials) to a secrets backend, and remove the plaintext copy.
+    MigrateSecret {
+        #[arg(long, help = "path of the plaintext secret")]
+        from: PathBuf,
+
+        #[arg(
+            long,
+            help = "path to store the secret at. The file name must be kept when the agent loads it with LoadCredentialEncrypted="
+        )]
+        to: PathBuf,
+
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = SecretsBackendKind::SystemdCreds,
+            help = "backend to store the secret with"
+        )]
+        secrets_backend: SecretsBackendKind,
+    },
 }
 
 // Include a set of useful diagnostic commands to aid support. We should work very hard to keep
@@ -82,4 +130,29 @@ pub enum SupportCommands {
         #[arg(long)]
         enable: bool,
     },
+    /// Export an encrypted bundle of diagnostic data (logs, inventory, configuration) to attach to a support ticket.
+    Bundle {
+        #[arg(long, help = "path of the encrypted bundle to write")]
+        output: PathBuf,
+
+        #[arg(
+            long,
+            help = "age public key of the support team, used to encrypt the bundle"
+        )]
+        recipient: String,
+
+        #[arg(
+            long,
+            help = "number of host agent log lines to include",
+            default_value = "1000"
+        )]
+        log_lines: u32,
+
+        #[arg(
+            long,
+            help = "path to the NATS leaf server configuration",
+            default_value = util_libs::nats_server::LEAF_SERVER_CONFIG_PATH
+        )]
+        leaf_server_config_path: PathBuf,
+    },
 }
@@ -12,6 +12,7 @@ pub async fn run(
     maybe_store_dir: &Option<PathBuf>,
     hub_url: String,
     hub_tls_insecure: bool,
+    js_domain: Option<String>,
 ) -> anyhow::Result<()> {
     let leaf_client_conn_domain = "127.0.0.1";
     let leaf_client_conn_port = std::env::var("NATS_LISTEN_PORT")
@@ -35,6 +36,7 @@ pub async fn run(
         max_memory_store: 1024 * 1024 * 1024, // 1 GB
         // TODO: make this configurable
         max_file_store: 1024 * 1024 * 1024, // 1 GB
+        domain: js_domain,
     };
 
     let logging_options = LoggingOptions {
@@ -1,4 +1,5 @@
 use crate::agent_cli::HostCommands;
+use crate::secrets;
 use hpos_hal::inventory::HoloInventory;
 
 pub fn host_command(command: &HostCommands) -> Result<(), std::io::Error> {
@@ -16,6 +17,16 @@ pub fn host_command(command: &HostCommands) -> Result<(), std::io::Error> {
                 }
             }
         }
+        HostCommands::MigrateSecret {
+            from,
+            to,
+            secrets_backend,
+        } => {
+            let backend = secrets::get_backend(secrets_backend);
+            secrets::migrate_plaintext(backend.as_ref(), from, to)
+                .map_err(std::io::Error::other)?;
+            println!("Secret moved from {} to {}", from.display(), to.display())
+        }
     }
     Ok(())
 }
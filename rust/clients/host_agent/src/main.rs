@@ -15,10 +15,15 @@ use agent_cli::DaemonzeArgs;
 use anyhow::Result;
 use clap::Parser;
 use dotenv::dotenv;
+use std::{sync::Arc, time::Duration};
+use util_libs::nats_health::DependencyCheck;
 pub mod agent_cli;
 pub mod gen_leaf_server;
 pub mod host_cmds;
+pub mod secrets;
+pub mod support_bundle;
 pub mod support_cmds;
+pub mod watchdog;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -49,24 +54,63 @@ async fn main() -> Result<(), AgentCliError> {
 
 async fn daemonize(args: &DaemonzeArgs) -> Result<(), async_nats::Error> {
     // let (host_pubkey, host_creds_path) = auth::initializer::run().await?;
+    let secrets_backend = secrets::get_backend(&args.secrets_backend);
+    let leafnode_client_creds_path = args
+        .nats_leafnode_client_creds_path
+        .as_ref()
+        .map(|path| secrets_backend.plaintext_path(path))
+        .transpose()?;
+
     let _ = gen_leaf_server::run(
-        &args.nats_leafnode_client_creds_path,
+        &leafnode_client_creds_path,
         &args.store_dir,
         args.hub_url.clone(),
         args.hub_tls_insecure,
+        args.leaf_js_domain.clone(),
     )
     .await;
 
-    let host_client = workload_manager::run(
-        "host_id_placeholder>",
-        &args.nats_leafnode_client_creds_path,
-        args.nats_connect_timeout_secs,
-    )
-    .await?;
+    let workload_heartbeat = watchdog::Heartbeat::default();
+    let host_client = Arc::new(
+        workload_manager::run(
+            "host_id_placeholder>",
+            &leafnode_client_creds_path,
+            &args.store_dir,
+            args.nats_connect_timeout_secs,
+            args.leaf_js_domain.clone(),
+            workload_heartbeat.clone(),
+        )
+        .await?,
+    );
+
+    let watchdog = match &args.watchdog_device {
+        Some(device_path) => {
+            let nats_client = host_client.clone();
+            let nats_check: DependencyCheck = Arc::new(move || {
+                let nats_client = nats_client.clone();
+                Box::pin(async move { nats_client.monitor().await.map_err(|e| anyhow::anyhow!(e)) })
+            });
+            // NB: The workload loop is considered wedged once it missed a few of its reports
+            let workload_check =
+                workload_heartbeat.check(workload_manager::WORKLOAD_HEALTH_REPORT_INTERVAL * 3);
+            Some(watchdog::spawn(
+                device_path,
+                Duration::from_secs(args.watchdog_interval_secs),
+                vec![
+                    ("nats".to_string(), nats_check),
+                    ("workloads".to_string(), workload_check),
+                ],
+            )?)
+        }
+        None => None,
+    };
 
     // Only exit program when explicitly requested
     tokio::signal::ctrl_c().await?;
-    
+
+    if let Some(watchdog) = watchdog {
+        watchdog.disarm().await;
+    }
     host_client.close().await?;
     Ok(())
 }
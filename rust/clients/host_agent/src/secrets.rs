@@ -0,0 +1,252 @@
+/*
+This module contains the backends used by the host agent to keep its credentials (eg: NATS creds) at rest.
+
+- `File`: the secret is stored as a plaintext file, readable only by its owner.
+- `SystemdCreds`: the secret is stored encrypted with `systemd-creds`, sealed to the TPM2 chip when the
+  host has one (otherwise to the host key in /var/lib/systemd/credential.secret).
+  When the agent is started with `LoadCredentialEncrypted=`, systemd decrypts the secret into
+  `$CREDENTIALS_DIRECTORY` and that copy is used directly. Otherwise the secret is only ever decrypted into the
+  (tmpfs backed) `$RUNTIME_DIRECTORY` of the service, which systemd removes when the agent stops.
+
+Existing plaintext secrets are moved to another backend with `host_agent host migrate-secret`.
+*/
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+#[derive(ValueEnum, Clone, Debug, Default)]
+pub enum SecretsBackendKind {
+    #[default]
+    File,
+    SystemdCreds,
+}
+
+pub trait SecretsBackend: Send + Sync {
+    /// Read the plaintext secret stored at `secret_path`
+    fn read(&self, secret_path: &Path) -> Result<Vec<u8>>;
+
+    /// Persist `secret` at `secret_path`, protected as strongly as the backend allows
+    fn write(&self, secret_path: &Path, secret: &[u8]) -> Result<()>;
+
+    /// Return a path to a plaintext copy of the secret.
+    /// NB: This is required by consumers that can only load credentials from disk (eg: the nats-server leafnode config).
+    fn plaintext_path(&self, secret_path: &Path) -> Result<PathBuf>;
+}
+
+pub fn get_backend(kind: &SecretsBackendKind) -> Box<dyn SecretsBackend> {
+    match kind {
+        SecretsBackendKind::File => Box::new(FileSecrets),
+        SecretsBackendKind::SystemdCreds => Box::new(SystemdCredsSecrets::default()),
+    }
+}
+
+// Helper function to write a file that is only readable by its owner
+fn write_private_file(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context(format!("creating {parent:?}"))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .context(format!("opening {path:?}"))?;
+    file.write_all(contents)
+        .context(format!("writing {path:?}"))?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct FileSecrets;
+
+impl SecretsBackend for FileSecrets {
+    fn read(&self, secret_path: &Path) -> Result<Vec<u8>> {
+        fs::read(secret_path).context(format!("reading {secret_path:?}"))
+    }
+
+    fn write(&self, secret_path: &Path, secret: &[u8]) -> Result<()> {
+        write_private_file(secret_path, secret)
+    }
+
+    fn plaintext_path(&self, secret_path: &Path) -> Result<PathBuf> {
+        Ok(secret_path.to_path_buf())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SystemdCredsSecrets {
+    // Value passed to `systemd-creds --with-key`. "auto" uses the TPM2 chip if one is available.
+    pub with_key: String,
+}
+
+impl Default for SystemdCredsSecrets {
+    fn default() -> Self {
+        Self {
+            with_key: "auto".to_string(),
+        }
+    }
+}
+
+impl SystemdCredsSecrets {
+    // The credential name is embedded in (and checked against) the encrypted blob, so it must stay stable.
+    fn credential_name(secret_path: &Path) -> Result<String> {
+        secret_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or(anyhow!("No file name found for secret at {secret_path:?}"))
+    }
+
+    // Copy of the secret already decrypted by systemd (via `LoadCredentialEncrypted=`), if any
+    fn systemd_loaded_path(name: &str) -> Option<PathBuf> {
+        let credentials_dir = std::env::var("CREDENTIALS_DIRECTORY").ok()?;
+        let path = PathBuf::from(credentials_dir).join(name);
+        path.exists().then_some(path)
+    }
+
+    // NB: There is no fallback to a persistent directory (eg: /tmp), as the decrypted secret would outlive the agent there
+    fn runtime_dir() -> Result<PathBuf> {
+        std::env::var("RUNTIME_DIRECTORY").map(PathBuf::from).map_err(|_| {
+            anyhow!("Neither CREDENTIALS_DIRECTORY nor RUNTIME_DIRECTORY is set. Start the agent with LoadCredentialEncrypted= or RuntimeDirectory=")
+        })
+    }
+}
+
+impl SecretsBackend for SystemdCredsSecrets {
+    fn read(&self, secret_path: &Path) -> Result<Vec<u8>> {
+        let name = Self::credential_name(secret_path)?;
+        if let Some(path) = Self::systemd_loaded_path(&name) {
+            return fs::read(&path).context(format!("reading {path:?}"));
+        }
+
+        let output = Command::new("systemd-creds")
+            .arg("decrypt")
+            .arg(format!("--name={name}"))
+            .arg(secret_path)
+            .arg("-")
+            .output()
+            .context("running systemd-creds decrypt")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Failed to decrypt {secret_path:?}: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    fn write(&self, secret_path: &Path, secret: &[u8]) -> Result<()> {
+        let name = Self::credential_name(secret_path)?;
+        if let Some(parent) = secret_path.parent() {
+            fs::create_dir_all(parent).context(format!("creating {parent:?}"))?;
+        }
+
+        let mut child = Command::new("systemd-creds")
+            .arg("encrypt")
+            .arg(format!("--name={name}"))
+            .arg(format!("--with-key={}", self.with_key))
+            .arg("-")
+            .arg(secret_path)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("running systemd-creds encrypt")?;
+        child
+            .stdin
+            .take()
+            .ok_or(anyhow!("Failed to open stdin of systemd-creds"))?
+            .write_all(secret)?;
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Failed to encrypt {secret_path:?}: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(())
+    }
+
+    fn plaintext_path(&self, secret_path: &Path) -> Result<PathBuf> {
+        let name = Self::credential_name(secret_path)?;
+        if let Some(path) = Self::systemd_loaded_path(&name) {
+            return Ok(path);
+        }
+
+        // Fall back to decrypting into the (tmpfs backed) runtime directory of the service
+        let plaintext_path = Self::runtime_dir()?.join(name);
+        let secret = self.read(secret_path)?;
+        write_private_file(&plaintext_path, &secret)?;
+        Ok(plaintext_path)
+    }
+}
+
+/// Move the plaintext secret at `plaintext_path` to `secret_path`, as stored by the given backend.
+/// The plaintext file is removed once the secret reads back from the backend, unless it is stored in place.
+pub fn migrate_plaintext(
+    backend: &dyn SecretsBackend,
+    plaintext_path: &Path,
+    secret_path: &Path,
+) -> Result<()> {
+    let secret = fs::read(plaintext_path).context(format!("reading {plaintext_path:?}"))?;
+    backend.write(secret_path, &secret)?;
+    if backend.read(secret_path)? != secret {
+        return Err(anyhow!(
+            "The secret stored at {secret_path:?} does not match {plaintext_path:?}"
+        ));
+    }
+
+    if plaintext_path != secret_path {
+        fs::remove_file(plaintext_path).context(format!("removing {plaintext_path:?}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_migrate_plaintext() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let plaintext_path = dir.path().join("plaintext.creds");
+        let secret_path = dir.path().join("secrets").join("leaf.creds");
+        fs::write(&plaintext_path, b"creds")?;
+
+        migrate_plaintext(&FileSecrets, &plaintext_path, &secret_path)?;
+        assert!(!plaintext_path.exists());
+        assert_eq!(FileSecrets.read(&secret_path)?, b"creds");
+        let mode = fs::metadata(&secret_path)?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        Ok(())
+    }
+
+    #[test]
+    fn test_systemd_creds_plaintext_path() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let secret_path = dir.path().join("leaf.creds");
+        let backend = SystemdCredsSecrets::default();
+
+        // the copy decrypted by systemd is used as is
+        let credentials_dir = dir.path().join("credentials");
+        fs::create_dir(&credentials_dir)?;
+        fs::write(credentials_dir.join("leaf.creds"), b"creds")?;
+        std::env::set_var("CREDENTIALS_DIRECTORY", &credentials_dir);
+        assert_eq!(
+            backend.plaintext_path(&secret_path)?,
+            credentials_dir.join("leaf.creds")
+        );
+
+        // without a tmpfs to decrypt the secret into, the secret is not decrypted at all
+        std::env::remove_var("CREDENTIALS_DIRECTORY");
+        std::env::remove_var("RUNTIME_DIRECTORY");
+        assert!(backend.plaintext_path(&secret_path).is_err());
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use crate::agent_cli::SupportCommands;
+use crate::support_bundle::SupportBundle;
 
 pub fn support_command(command: &SupportCommands) -> Result<(), std::io::Error> {
     // TODO: Fill these in under a separate set of commits to keep PRs simple.
@@ -18,6 +19,18 @@ pub fn support_command(command: &SupportCommands) -> Result<(), std::io::Error>
                 }
             }
         }
+        SupportCommands::Bundle {
+            output,
+            recipient,
+            log_lines,
+            leaf_server_config_path,
+        } => {
+            let bundle = SupportBundle::collect(*log_lines, leaf_server_config_path);
+            bundle
+                .write_encrypted(output, recipient)
+                .map_err(std::io::Error::other)?;
+            println!("Support bundle written to {}", output.display())
+        }
     }
     Ok(())
 }
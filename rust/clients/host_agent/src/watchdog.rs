@@ -0,0 +1,200 @@
+/*
+This module drives the hardware watchdog (eg: /dev/watchdog) of the host.
+
+The watchdog is only petted while every registered health check passes (eg: the NATS link is up, and the
+workload loop of the agent is still turning, as tracked by a `Heartbeat`).
+When the agent stays unhealthy for longer than the watchdog timeout configured in the kernel/firmware,
+the host reboots itself, which allows headless hosts in remote locations to recover from wedged states
+without a human intervening.
+NB: The device is disarmed with the "magic close" character when the agent shuts down cleanly,
+so stopping the agent on purpose does not reboot the host.
+NB: The responsiveness of the holochain conductor is not checked, as the agent does not manage a conductor yet.
+*/
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use util_libs::nats_health::DependencyCheck;
+
+const WATCHDOG_PET: &[u8] = b"\0";
+const WATCHDOG_MAGIC_CLOSE: &[u8] = b"V";
+
+// Time of the last iteration of a loop of the agent, so that a wedged loop fails the health checks
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    last_beat: Arc<Mutex<Instant>>,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            last_beat: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+}
+
+impl Heartbeat {
+    pub fn beat(&self) {
+        *self.last_beat.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    /// Health check that fails when the loop has not beaten for longer than `max_age`
+    pub fn check(&self, max_age: Duration) -> DependencyCheck {
+        let last_beat = self.last_beat.clone();
+        Arc::new(move || {
+            let age = last_beat
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .elapsed();
+            Box::pin(async move {
+                if age > max_age {
+                    return Err(anyhow!("No heartbeat for {age:?}"));
+                }
+                Ok(())
+            })
+        })
+    }
+}
+
+pub struct WatchdogHandle {
+    shutdown_tx: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl WatchdogHandle {
+    /// Stop petting the watchdog and disarm the device
+    pub async fn disarm(self) {
+        let _ = self.shutdown_tx.send(());
+        if let Err(e) = self.task.await {
+            log::error!("Watchdog task failed to shut down cleanly. Err={:?}", e);
+        }
+    }
+}
+
+/// Open the watchdog device and pet it every `interval`, as long as all `health_checks` pass
+pub fn spawn(
+    device_path: &Path,
+    interval: Duration,
+    health_checks: Vec<(String, DependencyCheck)>,
+) -> Result<WatchdogHandle> {
+    // NB: Opening the device arms the watchdog.
+    let mut device = OpenOptions::new()
+        .write(true)
+        .open(device_path)
+        .context(format!("opening watchdog device {device_path:?}"))?;
+    log::info!(
+        "Armed hardware watchdog. Device={:?}, Interval={:?}",
+        device_path,
+        interval
+    );
+
+    let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
+    let task = tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let failed_checks = run_health_checks(&health_checks).await;
+                    if failed_checks.is_empty() {
+                        if let Err(e) = write_to_device(&mut device, WATCHDOG_PET) {
+                            log::error!("Failed to pet hardware watchdog. Err={:?}", e);
+                        }
+                    } else {
+                        log::warn!(
+                            "Skipping hardware watchdog pet, health checks failed. Checks={:?}",
+                            failed_checks
+                        );
+                    }
+                }
+                _ = &mut shutdown_rx => {
+                    match write_to_device(&mut device, WATCHDOG_MAGIC_CLOSE) {
+                        Ok(()) => log::info!("Disarmed hardware watchdog."),
+                        Err(e) => log::error!("Failed to disarm hardware watchdog. Err={:?}", e),
+                    }
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(WatchdogHandle { shutdown_tx, task })
+}
+
+// Returns the names of the failing checks along with their error messages
+async fn run_health_checks(health_checks: &[(String, DependencyCheck)]) -> Vec<(String, String)> {
+    let mut failed_checks = vec![];
+    for (name, check) in health_checks.iter() {
+        if let Err(e) = check().await {
+            failed_checks.push((name.to_owned(), e.to_string()));
+        }
+    }
+    failed_checks
+}
+
+fn write_to_device(device: &mut File, bytes: &[u8]) -> Result<()> {
+    device.write_all(bytes)?;
+    device.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[tokio::test]
+    async fn test_pet_only_when_healthy() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let device_path = dir.path().join("watchdog");
+        File::create(&device_path)?;
+
+        let healthy = Arc::new(AtomicBool::new(false));
+        let is_healthy = healthy.clone();
+        let check: DependencyCheck = Arc::new(move || {
+            let healthy = is_healthy.load(Ordering::SeqCst);
+            Box::pin(async move {
+                if !healthy {
+                    return Err(anyhow!("unhealthy"));
+                }
+                Ok(())
+            })
+        });
+        let watchdog = spawn(
+            &device_path,
+            Duration::from_millis(10),
+            vec![("test".to_string(), check)],
+        )?;
+
+        // not petted while unhealthy...
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(std::fs::read(&device_path)?.is_empty());
+
+        // ...petted once healthy again, and disarmed on shutdown
+        healthy.store(true, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        watchdog.disarm().await;
+        let written = std::fs::read(&device_path)?;
+        assert!(written.len() > 1);
+        assert!(written[..written.len() - 1].iter().all(|b| *b == b'\0'));
+        assert_eq!(written.last(), Some(&b'V'));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_check() {
+        let heartbeat = Heartbeat::default();
+        let check = heartbeat.check(Duration::from_millis(20));
+        assert!(check().await.is_ok());
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(check().await.is_err());
+
+        heartbeat.beat();
+        assert!(check().await.is_ok());
+    }
+}
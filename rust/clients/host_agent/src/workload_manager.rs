@@ -8,30 +8,40 @@
     - installing new workloads
     - removing workloads
     - sending workload status upon request
+    - sending workload logs upon request
     - sending active periodic workload reports
 */
 
+use crate::watchdog::Heartbeat;
 use anyhow::{anyhow, Result};
 use async_nats::Message;
+use bson::doc;
 use mongodb::{options::ClientOptions, Client as MongoDBClient};
 use std::{path::PathBuf, sync::Arc, time::Duration};
 use util_libs::{
     db::mongodb::get_mongodb_url,
     js_stream_service::JsServiceParamsPartial,
+    nats_health::DependencyCheck,
     nats_js_client::{self, EndpointType},
 };
 use workload::{
-    WorkloadApi, WORKLOAD_SRV_DESC, WORKLOAD_SRV_NAME, WORKLOAD_SRV_SUBJ, WORKLOAD_SRV_VERSION,
+    ledger::CommandLedger, WorkloadApi, WORKLOAD_SRV_DESC, WORKLOAD_SRV_NAME, WORKLOAD_SRV_SUBJ,
+    WORKLOAD_SRV_VERSION,
 };
 
 const HOST_AGENT_CLIENT_NAME: &str = "Host Agent";
 const HOST_AGENT_INBOX_PREFIX: &str = "_host_inbox";
+pub(crate) const WORKLOAD_HEALTH_REPORT_INTERVAL: Duration = Duration::from_secs(30);
+const WORKLOAD_COMMAND_LEDGER_FILE_NAME: &str = "workload_commands.json";
 
 // TODO: Use _host_creds_path for auth once we add in the more resilient auth pattern.
 pub async fn run(
     host_pubkey: &str,
     host_creds_path: &Option<PathBuf>,
+    store_dir: &Option<PathBuf>,
     nats_connect_timeout_secs: u64,
+    js_domain: Option<String>,
+    heartbeat: Heartbeat,
 ) -> Result<nats_js_client::JsClient, async_nats::Error> {
     log::info!("HPOS Agent Client: Connecting to server...");
     log::info!("host_creds_path : {:?}", host_creds_path);
@@ -68,6 +78,7 @@ pub async fn run(
                     opts: vec![nats_js_client::with_event_listeners(event_listeners.clone())],
                     ping_interval: Some(Duration::from_secs(10)),
                     request_timeout: Some(Duration::from_secs(29)),
+                    js_domain: js_domain.clone(),
                 })
                 .await
                 .map_err(|e| anyhow::anyhow!("connecting to NATS via {nats_url}: {e}"));
@@ -96,7 +107,14 @@ pub async fn run(
     let client = MongoDBClient::with_options(client_options)?;
 
     // Generate the Workload API with access to db
-    let workload_api = WorkloadApi::new(&client).await?;
+    let mut workload_api = WorkloadApi::new(&client).await?;
+    workload_api.set_host_device_id(&host_workload_client.get_server_info());
+
+    // Persist the processed workload commands alongside the NATS store, so that duplicates are recognized across restarts
+    if let Some(store_dir) = store_dir {
+        workload_api.command_ledger =
+            CommandLedger::load(store_dir.join(WORKLOAD_COMMAND_LEDGER_FILE_NAME));
+    }
 
     // ==================== API ENDPOINTS ====================
     // Register Workload Streams for Host Agent to consume
@@ -132,6 +150,7 @@ pub async fn run(
         )
         .await?;
 
+    // NB: The uninstall is acknowledged to the orchestrator as a status update, so that it can complete the removal of the instance
     workload_service
         .add_local_consumer::<workload::types::ApiResult>(
             "uninstall_workload",
@@ -141,9 +160,72 @@ pub async fn run(
                     api.uninstall_workload(msg).await
                 }),
             ),
+            Some(Arc::new(|_| vec!["read_status_update".to_string()])),
+        )
+        .await?;
+
+    // NB: Logs are requested from a given host, so the subject is scoped to the host (ie: `WORKLOAD.<host_pubkey>.logs`)
+    workload_service
+        .add_local_consumer::<workload::types::LogsResult>(
+            "send_workload_logs",
+            &format!("{}.logs", host_pubkey),
+            EndpointType::Async(
+                workload_api.call(|api: WorkloadApi, msg: Arc<Message>| async move {
+                    api.send_workload_logs(msg).await
+                }),
+            ),
             None,
         )
         .await?;
 
+    // ==================== WORKLOAD HEALTH REPORTS ====================
+    // Periodically report whether the workloads with a health check are actually serving
+    // NB: Each report beats the heartbeat, so that the watchdog notices when this loop is wedged
+    let js = host_workload_client.js.clone();
+    let health_monitor = workload_api.health_monitor.clone();
+    let host_device_id = workload_api.host_device_id.clone();
+    tokio::spawn(async move {
+        let status_update_subject = format!("{}.read_status_update", WORKLOAD_SRV_SUBJ);
+        let mut ticker = tokio::time::interval(WORKLOAD_HEALTH_REPORT_INTERVAL);
+        loop {
+            ticker.tick().await;
+            heartbeat.beat();
+            for mut status in health_monitor.run_checks().await {
+                status.host_device_id = host_device_id.clone();
+                let result = match serde_json::to_vec(&status) {
+                    Ok(data) => js
+                        .publish(status_update_subject.clone(), data.into())
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| anyhow!(e)),
+                    Err(e) => Err(anyhow!(e)),
+                };
+                if let Err(e) = result {
+                    log::error!(
+                        "Failed to report workload health. Status={:?}, Err={:?}",
+                        status,
+                        e
+                    );
+                }
+            }
+        }
+    });
+
+    // ==================== HEALTH CHECK ====================
+    // Respond on `HEALTH.WORKLOAD.<host_pubkey>` with the service status and its dependencies
+    let mongodb_check: DependencyCheck = Arc::new(move || {
+        let client = client.clone();
+        Box::pin(async move {
+            client
+                .database("admin")
+                .run_command(doc! { "ping": 1 })
+                .await?;
+            Ok(())
+        })
+    });
+    host_workload_client
+        .add_health_responders(host_pubkey, vec![("mongodb".to_string(), mongodb_check)])
+        .await?;
+
     Ok(host_workload_client)
 }
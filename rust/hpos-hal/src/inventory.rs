@@ -11,6 +11,7 @@ use procfs::{CpuInfo, FromBufRead};
 use serde_derive::{Deserialize, Serialize};
 use std::fmt::{self, Display};
 use std::io;
+use std::process::Command;
 use std::{fs, fs::File};
 use thiserror::Error;
 use thiserror_context::{impl_context, Context};
@@ -26,6 +27,10 @@ pub enum InventoryErrorInner {
     UTF8(#[from] std::str::Utf8Error),
     #[error("Object not found")]
     NotFound,
+    #[error("JSON Error")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported inventory schema version {0}")]
+    UnsupportedSchemaVersion(u64),
 }
 impl_context!(InventoryError(InventoryErrorInner));
 
@@ -72,6 +77,13 @@ pub struct HoloInventory {
     /// An inventory of USB devices specifically. May overlap with other sections (eg, USB storage
     /// devices).
     pub usb: Vec<HoloUsbInventory>,
+    /// Information about GPUs (PCI display controllers) present.
+    #[serde(default)]
+    pub gpus: Vec<HoloGpuInventory>,
+    /// Readings of the temperature sensors exposed through hwmon (CPU packages, drives, chipsets,
+    /// etc).
+    #[serde(default)]
+    pub thermal: Vec<HoloThermalInventory>,
     /// Generally x86-specific SMBIOS/DMI information provided by the hardware vendor.
     pub smbios: HoloSMBIOS,
     /// An overall categorisation of this host as a platform. This might include guesses at the
@@ -413,6 +425,8 @@ impl HoloInventory {
             cpus: HoloProcessorInventory::from_host(),
             nics: HoloNicInventory::from_host(),
             usb: HoloUsbInventory::from_host(),
+            gpus: HoloGpuInventory::from_host(),
+            thermal: HoloThermalInventory::from_host(),
             platform: None,
         };
 
@@ -423,6 +437,91 @@ impl HoloInventory {
     }
 }
 
+/// The version of the `HoloInventory` schema produced by this crate. Bump this whenever a change to
+/// the inventory data structures can't be read by older consumers, and add a migration from the
+/// previous version to `HoloInventoryEnvelope::migrate`.
+pub const INVENTORY_SCHEMA_VERSION: u64 = 1;
+
+/// A versioned wrapper around `HoloInventory`, used when sending inventory to other services, so
+/// that an upgraded consumer can still read payloads produced by older agents.
+///
+/// ```rust
+/// use hpos_hal::inventory::{HoloInventory, HoloInventoryEnvelope};
+///
+/// let payload = serde_json::to_vec(&HoloInventoryEnvelope::new(HoloInventory::from_host())).unwrap();
+/// let envelope = HoloInventoryEnvelope::from_json(&payload).unwrap();
+/// ```
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HoloInventoryEnvelope {
+    pub schema_version: u64,
+    pub inventory: HoloInventory,
+}
+
+impl HoloInventoryEnvelope {
+    pub fn new(inventory: HoloInventory) -> Self {
+        HoloInventoryEnvelope {
+            schema_version: INVENTORY_SCHEMA_VERSION,
+            inventory,
+        }
+    }
+
+    /// Deserialize an inventory payload of any supported schema version, migrating it to the
+    /// current version. Payloads without an envelope are treated as version 0, which is what
+    /// agents sent before the envelope was introduced.
+    pub fn from_json(payload: &[u8]) -> Result<Self, InventoryError> {
+        let value: serde_json::Value = serde_json::from_slice(payload)
+            .map_err(|e| InventoryError::Base(InventoryErrorInner::Json(e)))?;
+        let (version, inventory) = match value.get("schema_version") {
+            Some(version) => {
+                let version =
+                    version
+                        .as_u64()
+                        .ok_or(InventoryError::Base(InventoryErrorInner::Json(
+                            serde::de::Error::custom("schema_version is not an unsigned integer"),
+                        )))?;
+                let inventory = value
+                    .get("inventory")
+                    .cloned()
+                    .ok_or(InventoryError::Base(InventoryErrorInner::NotFound))?;
+                (version, inventory)
+            }
+            None => (0, value),
+        };
+
+        let inventory = Self::migrate(version, inventory)?;
+        let inventory = serde_json::from_value(inventory)
+            .map_err(|e| InventoryError::Base(InventoryErrorInner::Json(e)))?;
+        Ok(HoloInventoryEnvelope::new(inventory))
+    }
+
+    /// Upgrade a serialized inventory from `version` to `INVENTORY_SCHEMA_VERSION`, one version at
+    /// a time.
+    fn migrate(
+        mut version: u64,
+        inventory: serde_json::Value,
+    ) -> Result<serde_json::Value, InventoryError> {
+        if version > INVENTORY_SCHEMA_VERSION {
+            return Err(InventoryError::Base(
+                InventoryErrorInner::UnsupportedSchemaVersion(version),
+            ));
+        }
+        while version < INVENTORY_SCHEMA_VERSION {
+            match version {
+                // Version 0 is the bare inventory, which predates GPUs, thermal sensors and SMART
+                // data. Those fields are optional, so the data structure itself needs no changes.
+                0 => {}
+                _ => {
+                    return Err(InventoryError::Base(
+                        InventoryErrorInner::UnsupportedSchemaVersion(version),
+                    ))
+                }
+            }
+            version += 1;
+        }
+        Ok(inventory)
+    }
+}
+
 /// Data structure representing physical drives, and the partitions within them. Virtual device,
 /// such as loopback block devices, aren't tracked in this list. Only physical drives.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -448,6 +547,64 @@ pub struct HoloDriveInventory {
     pub partitions: Vec<HoloPartitionInventory>,
     /// Whole-device filesystem, if present
     pub filesystem: Option<HoloFilesystemInventory>,
+    /// Drive health as reported by SMART, if available.
+    #[serde(default)]
+    pub smart: Option<HoloSmartInventory>,
+}
+
+/// Drive health attributes reported by SMART. These are read via `smartctl` (smartmontools), as
+/// the kernel doesn't expose them through sysfs.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HoloSmartInventory {
+    /// Overall health self-assessment of the drive. A drive that fails it is about to fail (or
+    /// already has).
+    pub passed: Option<bool>,
+    /// Current drive temperature in degrees celsius.
+    pub temperature_celsius: Option<u64>,
+    /// Number of hours the drive has been powered on.
+    pub power_on_hours: Option<u64>,
+}
+
+impl HoloSmartInventory {
+    /// Read the SMART attributes of the given block device. Returns `None` if smartmontools isn't
+    /// installed, or the drive doesn't support SMART.
+    pub fn from_host(block_dev: &str) -> Option<HoloSmartInventory> {
+        let output = match Command::new("smartctl")
+            .arg("--json")
+            .arg("--health")
+            .arg("--attributes")
+            .arg(format!("/dev/{}", block_dev))
+            .output()
+        {
+            Ok(output) => output,
+            Err(e) => {
+                info!("Failed to run smartctl for {}: {}", block_dev, e);
+                return None;
+            }
+        };
+
+        // NB: smartctl uses a bitmask exit status, which is non-zero for failing drives, so we
+        // rely on the JSON output instead.
+        let report: serde_json::Value = match serde_json::from_slice(&output.stdout) {
+            Ok(report) => report,
+            Err(e) => {
+                info!("Failed to parse smartctl output for {}: {}", block_dev, e);
+                return None;
+            }
+        };
+
+        let passed = report["smart_status"]["passed"].as_bool();
+        if passed.is_none() {
+            debug!("No SMART health status reported for {}", block_dev);
+            return None;
+        }
+
+        Some(HoloSmartInventory {
+            passed,
+            temperature_celsius: report["temperature"]["current"].as_u64(),
+            power_on_hours: report["power_on_time"]["hours"].as_u64(),
+        })
+    }
 }
 
 /// Glob used to find block devices that are hardware-backed. This primarily consists of
@@ -490,6 +647,8 @@ impl HoloDriveInventory {
                 None
             };
 
+            let smart = HoloSmartInventory::from_host(&block_dev);
+
             ret.push(HoloDriveInventory {
                 block_dev: block_dev.to_string(),
                 serial,
@@ -501,6 +660,7 @@ impl HoloDriveInventory {
                 capacity_bytes,
                 partitions,
                 filesystem,
+                smart,
             })
         }
         ret
@@ -642,6 +802,96 @@ impl HoloNicInventory {
     }
 }
 
+/// A representation of a GPU, ie: a PCI display controller.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HoloGpuInventory {
+    /// Hardware vendor ID. See `pci.ids` for mapping to a string.
+    pub vendor: Option<String>,
+    /// Hardware model ID. See `pci.ids` for mapping to a string.
+    pub model: Option<String>,
+    /// Kernel driver bound to the device, if any.
+    pub driver: Option<String>,
+    /// Dedicated video memory in bytes. Only reported by some drivers (eg, amdgpu).
+    pub vram_bytes: Option<u64>,
+    /// Location within the hardware tree for the device.
+    pub location: String,
+}
+
+impl HoloGpuInventory {
+    const PCI_DEV_GLOB: &str = "/sys/bus/pci/devices/*";
+    /// PCI base class for display controllers (VGA, 3D and other display controllers).
+    const DISPLAY_CLASS_PREFIX: &str = "0x03";
+
+    pub fn from_host() -> Vec<HoloGpuInventory> {
+        let mut ret: Vec<HoloGpuInventory> = vec![];
+
+        for pci_dev in glob(Self::PCI_DEV_GLOB).unwrap() {
+            let pci_dev = pci_dev.unwrap().clone();
+            let dev_base = pci_dev.to_string_lossy();
+            let class = sysfs::string_attr(format!("{}/class", dev_base));
+            if !class.is_some_and(|class| class.starts_with(Self::DISPLAY_CLASS_PREFIX)) {
+                continue;
+            }
+
+            debug!("Adding GPU {}", dev_base);
+            let driver = fs::read_link(format!("{}/driver", dev_base))
+                .ok()
+                .and_then(|driver| {
+                    driver
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                });
+
+            ret.push(HoloGpuInventory {
+                vendor: sysfs::string_attr(format!("{}/vendor", dev_base)),
+                model: sysfs::string_attr(format!("{}/device", dev_base)),
+                driver,
+                vram_bytes: sysfs::integer_attr(format!("{}/mem_info_vram_total", dev_base)),
+                location: sysfs::path_by_device_link(&dev_base),
+            })
+        }
+
+        ret
+    }
+}
+
+/// A reading from a temperature sensor.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HoloThermalInventory {
+    /// Name of the hwmon device providing the sensor (eg, `coretemp`, `nvme`, `k10temp`).
+    pub sensor: String,
+    /// Label of the sensor within its device (eg, `Package id 0`), if provided.
+    pub label: Option<String>,
+    /// Current temperature in millidegrees celsius.
+    pub temp_millicelsius: Option<u64>,
+    /// Critical temperature threshold in millidegrees celsius, if provided.
+    pub crit_millicelsius: Option<u64>,
+}
+
+impl HoloThermalInventory {
+    const HWMON_TEMP_GLOB: &str = "/sys/class/hwmon/hwmon*/temp*_input";
+
+    pub fn from_host() -> Vec<HoloThermalInventory> {
+        let mut ret: Vec<HoloThermalInventory> = vec![];
+
+        for temp_input in glob(Self::HWMON_TEMP_GLOB).unwrap() {
+            let temp_input = temp_input.unwrap().to_string_lossy().to_string();
+            // eg, /sys/class/hwmon/hwmon0/temp1_input -> /sys/class/hwmon/hwmon0/temp1
+            let temp_base = temp_input.strip_suffix("_input").unwrap_or_default();
+            let hwmon_base = temp_base.rsplit_once("/").unwrap_or_default().0;
+
+            ret.push(HoloThermalInventory {
+                sensor: sysfs::string_attr(format!("{}/name", hwmon_base)).unwrap_or_default(),
+                label: sysfs::string_attr(format!("{}_label", temp_base)),
+                temp_millicelsius: sysfs::integer_attr(temp_input.clone()),
+                crit_millicelsius: sysfs::integer_attr(format!("{}_crit", temp_base)),
+            })
+        }
+
+        ret
+    }
+}
+
 /// Data structure representing a node CPU. We currently only grab a few fields that we use
 /// elsewhere, but will likely want to add to the list of CPU attributes we harvest.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -1,4 +1,4 @@
-use crate::inventory::HoloInventory;
+use crate::inventory::{HoloInventory, HoloInventoryEnvelope, INVENTORY_SCHEMA_VERSION};
 use std::process::Command;
 
 #[test]
@@ -7,6 +7,33 @@ fn from_host() {
     //eprintln!("Inventory: {:?}", inv);
 }
 
+#[test]
+fn inventory_envelope() {
+    let inv = HoloInventory::from_host();
+    let inv_json = serde_json::to_value(&inv).unwrap();
+    let payload = serde_json::to_vec(&HoloInventoryEnvelope::new(inv)).unwrap();
+    let envelope = HoloInventoryEnvelope::from_json(&payload).unwrap();
+    assert_eq!(envelope.schema_version, INVENTORY_SCHEMA_VERSION);
+    assert_eq!(serde_json::to_value(&envelope.inventory).unwrap(), inv_json);
+
+    // Bare inventories sent by agents that predate the envelope (and the GPU/thermal data)
+    let mut legacy = inv_json;
+    let legacy_obj = legacy.as_object_mut().unwrap();
+    legacy_obj.remove("gpus");
+    legacy_obj.remove("thermal");
+    let payload = serde_json::to_vec(&legacy).unwrap();
+    let envelope = HoloInventoryEnvelope::from_json(&payload).unwrap();
+    assert_eq!(envelope.schema_version, INVENTORY_SCHEMA_VERSION);
+    assert!(envelope.inventory.gpus.is_empty());
+
+    let future = serde_json::json!({
+        "schema_version": INVENTORY_SCHEMA_VERSION + 1,
+        "inventory": {},
+    });
+    let payload = serde_json::to_vec(&future).unwrap();
+    assert!(HoloInventoryEnvelope::from_json(&payload).is_err());
+}
+
 #[test]
 fn parse_fat32() {
     std::fs::create_dir_all("target").unwrap();
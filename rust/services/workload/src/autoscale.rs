@@ -0,0 +1,65 @@
+/*
+This module decides how many hosts a workload with an autoscaling policy should run on, from the request rate
+the gateway reports for it.
+
+The workload scales out as soon as its hosts would each serve more than `target_requests_per_host`,
+but only scales in once the remaining hosts would each serve less than `SCALE_IN_THRESHOLD` of the target,
+so that a workload whose traffic hovers around a threshold does not keep on being installed and removed.
+*/
+
+use util_libs::db::schemas::AutoscalingPolicy;
+
+pub const SCALE_IN_THRESHOLD: f64 = 0.75;
+
+/// Number of hosts the workload should run on, given the number of hosts it runs on and its request rate
+pub fn desired_host_count(
+    policy: &AutoscalingPolicy,
+    current_hosts: u16,
+    requests_per_sec: f64,
+) -> u16 {
+    let min_hosts = policy.min_hosts.max(1);
+    let max_hosts = policy.max_hosts.max(min_hosts);
+    if policy.target_requests_per_host <= 0.0 {
+        return current_hosts.clamp(min_hosts, max_hosts);
+    }
+
+    let needed = (requests_per_sec.max(0.0) / policy.target_requests_per_host).ceil() as u16;
+    let desired = if needed > current_hosts {
+        needed
+    } else {
+        // Remove hosts one at a time, for as long as the remaining hosts stay well under the target
+        let mut desired = current_hosts;
+        while desired > needed.max(1)
+            && requests_per_sec
+                < policy.target_requests_per_host * (desired - 1) as f64 * SCALE_IN_THRESHOLD
+        {
+            desired -= 1;
+        }
+        desired
+    };
+    desired.clamp(min_hosts, max_hosts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_desired_host_count() {
+        let policy = AutoscalingPolicy {
+            min_hosts: 2,
+            max_hosts: 10,
+            target_requests_per_host: 100.0,
+        };
+
+        // Scale out to serve the traffic, up to the maximum
+        assert_eq!(desired_host_count(&policy, 2, 450.0), 5);
+        assert_eq!(desired_host_count(&policy, 2, 5000.0), 10);
+
+        // Hold on to the hosts while the traffic stays close to the target...
+        assert_eq!(desired_host_count(&policy, 5, 350.0), 5);
+        // ...and scale in once it drops well below it, down to the minimum
+        assert_eq!(desired_host_count(&policy, 5, 200.0), 3);
+        assert_eq!(desired_host_count(&policy, 5, 0.0), 2);
+    }
+}
@@ -0,0 +1,162 @@
+/*
+This module runs the health checks of the workloads installed on a host, so that the orchestrator can tell
+a workload that is merely installed (`Running`) from one that is actually serving (`Healthy`).
+
+The host agent registers the health check of a workload when the workload is started, and periodically
+reports the result of every registered check as a workload status.
+*/
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use url::Url;
+use util_libs::db::schemas::{HealthCheck, MongoDbId, WorkloadState, WorkloadStatus};
+
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Default)]
+pub struct HealthMonitor {
+    checks: Arc<Mutex<HashMap<MongoDbId, HealthCheck>>>,
+}
+
+impl HealthMonitor {
+    pub fn register(&self, workload_id: MongoDbId, check: HealthCheck) {
+        let mut checks = self.checks.lock().unwrap_or_else(|e| e.into_inner());
+        checks.insert(workload_id, check);
+    }
+
+    pub fn unregister(&self, workload_id: &MongoDbId) {
+        let mut checks = self.checks.lock().unwrap_or_else(|e| e.into_inner());
+        checks.remove(workload_id);
+    }
+
+    /// Run every registered health check, and return the resulting status of each workload
+    pub async fn run_checks(&self) -> Vec<WorkloadStatus> {
+        let checks: Vec<(MongoDbId, HealthCheck)> = self
+            .checks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(id, check)| (id.clone(), check.clone()))
+            .collect();
+
+        let mut statuses = vec![];
+        for (workload_id, check) in checks {
+            let actual = match probe(&check).await {
+                Ok(()) => WorkloadState::Healthy,
+                Err(e) => WorkloadState::Unhealthy(format!("{e:#}")),
+            };
+            statuses.push(WorkloadStatus {
+                id: Some(workload_id),
+                host_device_id: None,
+                desired: WorkloadState::Healthy,
+                actual,
+            });
+        }
+        statuses
+    }
+}
+
+/// Run a single health check
+pub async fn probe(check: &HealthCheck) -> Result<()> {
+    let probe = async {
+        match check {
+            HealthCheck::Http { url } => probe_http(url).await,
+            HealthCheck::Tcp { port } => {
+                TcpStream::connect(("127.0.0.1", *port))
+                    .await
+                    .context(format!("connecting to port {port}"))?;
+                Ok(())
+            }
+            HealthCheck::ZomeCall { .. } => probe_http(&zome_call_url(check)?).await,
+        }
+    };
+    tokio::time::timeout(HEALTH_CHECK_TIMEOUT, probe)
+        .await
+        .map_err(|_| anyhow!("Health check timed out after {:?}", HEALTH_CHECK_TIMEOUT))?
+}
+
+/// Url of hc-http-gw that makes the zome call of the health check
+/// NB: hc-http-gw responds with an error status when the zome call fails, so the zome call is probed like any http endpoint
+pub fn zome_call_url(check: &HealthCheck) -> Result<String> {
+    let HealthCheck::ZomeCall {
+        gateway_port,
+        dna_hash,
+        coordinator_identifier,
+        zome_name,
+        fn_name,
+    } = check
+    else {
+        return Err(anyhow!("Not a zome call health check: {check:?}"));
+    };
+    Ok(format!(
+        "http://127.0.0.1:{gateway_port}/{dna_hash}/{coordinator_identifier}/{zome_name}/{fn_name}"
+    ))
+}
+
+// Send a plain HTTP/1.1 GET request and expect a 2xx response
+// NB: Health check endpoints are served locally (eg: by hc-http-gw), so TLS is not supported
+async fn probe_http(url: &str) -> Result<()> {
+    let url = Url::parse(url).context(format!("parsing health check url {url}"))?;
+    if url.scheme() != "http" {
+        return Err(anyhow!(
+            "Unsupported health check url scheme: {}",
+            url.scheme()
+        ));
+    }
+    let host = url
+        .host_str()
+        .ok_or(anyhow!("No host in health check url {url}"))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    let mut stream = TcpStream::connect((host, port))
+        .await
+        .context(format!("connecting to {host}:{port}"))?;
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let request = format!("GET {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = vec![];
+    stream.read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status_code = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or(anyhow!("Invalid HTTP response: {status_line}"))?;
+    if !(200..300).contains(&status_code) {
+        return Err(anyhow!(
+            "Health check endpoint responded with {status_line}"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zome_call_url() -> Result<()> {
+        let check = HealthCheck::ZomeCall {
+            gateway_port: 8090,
+            dna_hash: "uhC0kabc".to_string(),
+            coordinator_identifier: "forum".to_string(),
+            zome_name: "posts".to_string(),
+            fn_name: "ping".to_string(),
+        };
+        assert_eq!(
+            zome_call_url(&check)?,
+            "http://127.0.0.1:8090/uhC0kabc/forum/posts/ping"
+        );
+        assert!(zome_call_url(&HealthCheck::Tcp { port: 8090 }).is_err());
+        Ok(())
+    }
+}
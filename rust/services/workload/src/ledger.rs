@@ -0,0 +1,167 @@
+/*
+This module keeps track of the workload commands a host has already processed, so that a command JetStream
+delivers more than once (eg: when the ack of the first delivery is lost) is not run twice.
+
+Commands are identified by their `Nats-Msg-Id` header, which the publisher sets (and which JetStream also uses
+to drop duplicate publishes within the duplicate window of the stream).
+NB: Only the last `MAX_LEDGER_ENTRIES` commands are remembered. Redeliveries happen shortly after the original
+delivery, so older commands do not need to be kept around.
+
+The ledger is persisted by writing it to a temporary file that replaces the previous ledger, so that a crash
+never leaves a truncated ledger behind. A ledger that cannot be read is dropped (with a warning), which at worst
+lets a command that was redelivered across the restart run twice.
+*/
+
+use anyhow::{Context, Result};
+use async_nats::{header::NATS_MESSAGE_ID, Message};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub const MAX_LEDGER_ENTRIES: usize = 1024;
+
+#[derive(Debug, Clone, Default)]
+pub struct CommandLedger {
+    processed: Arc<Mutex<VecDeque<String>>>,
+    path: Option<PathBuf>, // When set, the ledger is persisted so that it survives restarts of the host agent
+    write_lock: Arc<tokio::sync::Mutex<()>>, // Keeps the ledger writes in the order of their changes
+}
+
+impl CommandLedger {
+    /// Load the ledger persisted at `path`, or start an empty one if there is none yet (or it cannot be read)
+    pub fn load(path: PathBuf) -> Self {
+        let processed = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                log::warn!(
+                    "Dropping corrupt command ledger. Path={:?}, Err={:?}",
+                    path,
+                    e
+                );
+                VecDeque::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => VecDeque::new(),
+            Err(e) => {
+                log::warn!(
+                    "Failed to read command ledger. Starting an empty one. Path={:?}, Err={:?}",
+                    path,
+                    e
+                );
+                VecDeque::new()
+            }
+        };
+        Self {
+            processed: Arc::new(Mutex::new(processed)),
+            path: Some(path),
+            write_lock: Arc::default(),
+        }
+    }
+
+    /// Id of the command carried by the message, if the publisher set one
+    pub fn command_id(msg: &Message) -> Option<String> {
+        msg.headers
+            .as_ref()?
+            .get(NATS_MESSAGE_ID)
+            .map(|id| id.as_str().to_string())
+    }
+
+    pub fn is_processed(&self, command_id: &str) -> bool {
+        let processed = self.processed.lock().unwrap_or_else(|e| e.into_inner());
+        processed.iter().any(|id| id == command_id)
+    }
+
+    /// Remember a command once it was processed successfully
+    pub async fn record(&self, command_id: String) -> Result<()> {
+        // NB: The ledger is serialized while holding the write lock, so that an older ledger never overwrites a newer one
+        let _write_guard = self.write_lock.lock().await;
+        let bytes = {
+            let mut processed = self.processed.lock().unwrap_or_else(|e| e.into_inner());
+            if processed.contains(&command_id) {
+                return Ok(());
+            }
+            processed.push_back(command_id);
+            while processed.len() > MAX_LEDGER_ENTRIES {
+                processed.pop_front();
+            }
+            serde_json::to_vec(&*processed)?
+        };
+
+        if let Some(path) = self.path.clone() {
+            tokio::task::spawn_blocking(move || write_atomically(&path, &bytes))
+                .await?
+                .context("writing command ledger")?;
+        }
+        Ok(())
+    }
+}
+
+// Helper function to replace the file at `path`, without ever leaving a partially written file behind
+fn write_atomically(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let mut file = std::fs::File::create(&tmp_path).context(format!("creating {tmp_path:?}"))?;
+    file.write_all(bytes)
+        .context(format!("writing {tmp_path:?}"))?;
+    file.sync_all().context(format!("syncing {tmp_path:?}"))?;
+    std::fs::rename(&tmp_path, path).context(format!("renaming {tmp_path:?} to {path:?}"))?;
+
+    // NB: The directory is synced too, so that the rename itself survives a crash
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .context(format!("syncing {dir:?}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_command_ledger() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("commands.json");
+
+        let ledger = CommandLedger::load(path.clone());
+        assert!(!ledger.is_processed("cmd-1"));
+        ledger.record("cmd-1".to_string()).await?;
+        ledger.record("cmd-1".to_string()).await?;
+        assert!(ledger.is_processed("cmd-1"));
+
+        // The ledger survives a restart
+        let ledger = CommandLedger::load(path.clone());
+        assert!(ledger.is_processed("cmd-1"));
+
+        for i in 0..MAX_LEDGER_ENTRIES {
+            ledger.record(format!("cmd-{}", i + 2)).await?;
+        }
+        assert!(!ledger.is_processed("cmd-1"));
+        assert!(ledger.is_processed("cmd-2"));
+        assert_eq!(
+            CommandLedger::load(path).processed.lock().unwrap().len(),
+            MAX_LEDGER_ENTRIES
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_command_ledger() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("commands.json");
+
+        // A truncated ledger is dropped instead of failing the start of the host agent...
+        std::fs::write(&path, r#"["cmd-1", "cm"#)?;
+        let ledger = CommandLedger::load(path.clone());
+        assert!(!ledger.is_processed("cmd-1"));
+
+        // ...and replaced on the next command
+        ledger.record("cmd-2".to_string()).await?;
+        let ledger = CommandLedger::load(path.clone());
+        assert!(ledger.is_processed("cmd-2"));
+        assert!(!dir.path().join("commands.json.tmp").exists());
+        Ok(())
+    }
+}
@@ -6,27 +6,49 @@ Users: orchestrator & hpos
 Endpoints & Managed Subjects:
 - `add_workload`: handles the "WORKLOAD.add" subject
 - `remove_workload`: handles the "WORKLOAD.remove" subject
+- `set_maintenance`: handles the "WORKLOAD.maintenance" subject
+- `cordon_host`: handles the "WORKLOAD.orchestrator.cordon_host" subject
+- `drain_host`: handles the "WORKLOAD.orchestrator.drain_host" subject
+- `get_job_events`: handles the "WORKLOAD.events" subject
+- `get_routing_table`: handles the "WORKLOAD.routes" subject
+- `handle_host_health_transition`: handles the "WORKLOAD.gateway_host_health" subject
+- `handle_traffic_metrics`: handles the "WORKLOAD.gateway_metrics" subject
 - Partial: `handle_db_change`: handles the "WORKLOAD.handle_change" subject // the stream changed output by the mongo<>nats connector (stream eg: DB_COLL_CHANGE_WORKLOAD).
 - TODO: `start_workload`: handles the "WORKLOAD.start.{{hpos_id}}" subject
 - TODO: `send_workload_status`: handles the "WORKLOAD.send_status.{{hpos_id}}" subject
 - TODO: `uninstall_workload`: handles the "WORKLOAD.uninstall.{{hpos_id}}" subject
+- `send_workload_logs`: handles the "WORKLOAD.{{hpos_id}}.logs" subject
 */
 
+pub mod autoscale;
+pub mod health;
+pub mod ledger;
+pub mod logs;
+pub mod quota;
+pub mod reconcile;
+pub mod retry;
+pub mod rollout;
+pub mod scheduler;
 pub mod types;
+pub mod validation;
 
 use anyhow::{anyhow, Result};
-use async_nats::Message;
-use bson::{self, doc, to_document};
+use async_nats::{Message, ServerInfo};
+use bson::{self, doc, oid::ObjectId, to_document, Bson};
 use mongodb::{options::UpdateModifications, Client as MongoDBClient};
-use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 use std::future::Future;
-use std::{fmt::Debug, sync::Arc};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+use types::RequestContext;
 use util_libs::{
     db::{
         mongodb::{IntoIndexes, MongoCollection, MongoDbAPI},
-        schemas::{self, Host, Workload, WorkloadState, WorkloadStatus},
+        schemas::{
+            self, Host, HostTrust, Job, JobEvent, JobEventSource, Network, SchedulingPolicy,
+            Workload, WorkloadState, WorkloadStatus,
+        },
     },
+    js_stream_service::EndpointTraits,
     nats_js_client,
 };
 
@@ -35,11 +57,23 @@ pub const WORKLOAD_SRV_SUBJ: &str = "WORKLOAD";
 pub const WORKLOAD_SRV_VERSION: &str = "0.0.1";
 pub const WORKLOAD_SRV_DESC: &str = "This service handles the flow of Workload requests between the Developer and the Orchestrator, and between the Orchestrator and HPOS.";
 
+// Hosts with a lower trust score are no longer assigned new workloads
+pub const MIN_HOST_TRUST_SCORE: f64 = 0.5;
+
 #[derive(Debug, Clone)]
 pub struct WorkloadApi {
     pub workload_collection: MongoCollection<schemas::Workload>,
     pub host_collection: MongoCollection<schemas::Host>,
     pub user_collection: MongoCollection<schemas::User>,
+    pub developer_collection: MongoCollection<schemas::Developer>,
+    pub job_collection: MongoCollection<schemas::Job>,
+    pub job_event_collection: MongoCollection<schemas::JobEvent>,
+    pub policy_collection: MongoCollection<schemas::SchedulingPolicy>,
+    pub scheduler: scheduler::Scheduler,
+    pub reconciler: reconcile::ReconcilerConfig,
+    pub health_monitor: health::HealthMonitor,
+    pub command_ledger: ledger::CommandLedger,
+    pub host_device_id: Option<String>, // Set when run by the host agent, so that the statuses it reports say which host they come from
 }
 
 impl WorkloadApi {
@@ -49,17 +83,39 @@ impl WorkloadApi {
                 .await?,
             host_collection: Self::init_collection(client, schemas::HOST_COLLECTION_NAME).await?,
             user_collection: Self::init_collection(client, schemas::USER_COLLECTION_NAME).await?,
+            developer_collection: Self::init_collection(client, schemas::DEVELOPER_COLLECTION_NAME)
+                .await?,
+            job_collection: Self::init_collection(client, schemas::JOB_COLLECTION_NAME).await?,
+            job_event_collection: Self::init_collection(client, schemas::JOB_EVENT_COLLECTION_NAME)
+                .await?,
+            policy_collection: Self::init_collection(
+                client,
+                schemas::SCHEDULING_POLICY_COLLECTION_NAME,
+            )
+            .await?,
+            scheduler: scheduler::Scheduler::default(),
+            reconciler: reconcile::ReconcilerConfig::default(),
+            health_monitor: health::HealthMonitor::default(),
+            command_ledger: ledger::CommandLedger::default(),
+            host_device_id: None,
         })
     }
 
-    pub fn call<F, Fut>(&self, handler: F) -> nats_js_client::AsyncEndpointHandler<types::ApiResult>
+    // Helper function to identify the host the api is run by, as the host agent
+    // NB: Hosts are registered under the ID of their NATS (leaf) server (see `Host.device_id`), which the orchestrator looks them up by
+    pub fn set_host_device_id(&mut self, server_info: &ServerInfo) {
+        self.host_device_id = Some(server_info.server_id.clone());
+    }
+
+    pub fn call<F, Fut, R>(&self, handler: F) -> nats_js_client::AsyncEndpointHandler<R>
     where
         F: Fn(WorkloadApi, Arc<Message>) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Result<types::ApiResult, anyhow::Error>> + Send + 'static,
+        Fut: Future<Output = Result<R, anyhow::Error>> + Send + 'static,
+        R: EndpointTraits,
     {
         let api = self.to_owned();
         Arc::new(
-            move |msg: Arc<Message>| -> nats_js_client::JsServiceResponse<types::ApiResult> {
+            move |msg: Arc<Message>| -> nats_js_client::JsServiceResponse<R> {
                 let api_clone = api.clone();
                 Box::pin(handler(api_clone, msg))
             },
@@ -74,6 +130,8 @@ impl WorkloadApi {
                 msg,
                 WorkloadState::Reported,
                 |workload: schemas::Workload| async move {
+                    validation::validate_workload(&workload)?;
+                    self.check_developer_quota(&workload).await?;
                     let workload_id = self
                         .workload_collection
                         .insert_one_into(workload.clone())
@@ -89,13 +147,13 @@ impl WorkloadApi {
                     Ok(types::ApiResult(
                         WorkloadStatus {
                             id: updated_workload._id,
+                            host_device_id: None,
                             desired: WorkloadState::Reported,
                             actual: WorkloadState::Reported,
                         },
                         None,
                     ))
                 },
-                WorkloadState::Error,
             )
             .await)
     }
@@ -110,6 +168,8 @@ impl WorkloadApi {
                 msg,
                 WorkloadState::Running,
                 |workload: schemas::Workload| async move {
+                    validation::validate_workload(&workload)?;
+                    self.check_developer_quota(&workload).await?;
                     let workload_query = doc! { "_id":  workload._id.clone() };
                     let updated_workload = to_document(&workload)?;
                     self.workload_collection
@@ -125,13 +185,13 @@ impl WorkloadApi {
                     Ok(types::ApiResult(
                         WorkloadStatus {
                             id: workload._id,
+                            host_device_id: None,
                             desired: WorkloadState::Reported,
                             actual: WorkloadState::Reported,
                         },
                         None,
                     ))
                 },
-                WorkloadState::Error,
             )
             .await)
     }
@@ -145,22 +205,334 @@ impl WorkloadApi {
             msg,
             WorkloadState::Removed,
             |workload_id: schemas::MongoDbId| async move {
+                // Workloads are torn down in the reverse order of their dependencies,
+                // ...so refuse to remove a workload while other workloads still depend on it
+                let dependents_query = doc! { "dependencies": workload_id.clone() };
+                let dependents = self.workload_collection.get_many_from(dependents_query).await.for_workload(&workload_id)?;
+                if !dependents.is_empty() {
+                    let dependent_ids: Vec<schemas::MongoDbId> = dependents.into_iter().filter_map(|w| w._id).collect();
+                    let err_msg = format!("Unable to remove workload while other workloads depend on it. MongodDB Workload ID={:?}, Dependent Workload IDs={:?}", workload_id, dependent_ids);
+                    return Err(anyhow!(err_msg)).for_workload(&workload_id);
+                }
+
                 let workload_query = doc! { "_id":  workload_id.clone() };
-                self.workload_collection.delete_one_from(workload_query).await?;
+                self.workload_collection.delete_one_from(workload_query).await.for_workload(&workload_id)?;
                 log::info!(
                     "Successfully removed workload from the Workload Collection. MongodDB Workload ID={:?}",
                     workload_id
                 );
+
+                // Ask every instance of the workload to be torn down
+                let jobs_query = doc! { "workload_id": workload_id.clone() };
+                let removed_jobs_doc = doc! { "$set": { "desired_state": bson::to_bson(&WorkloadState::Removed)? } };
+                for job in self.job_collection.get_many_from(jobs_query).await.for_workload(&workload_id)? {
+                    let job_query = doc! { "_id": job._id };
+                    self.job_collection.update_one_within(job_query, UpdateModifications::Document(removed_jobs_doc.clone())).await.for_workload(&workload_id)?;
+                }
                 Ok(types::ApiResult(
                     WorkloadStatus {
                         id: Some(workload_id),
+                        host_device_id: None,
                         desired: WorkloadState::Removed,
                         actual: WorkloadState::Removed,
                     },
                     None
                 ))
             },
-            WorkloadState::Error,
+        )
+        .await)
+    }
+
+    // NB: While in maintenance, the workload keeps running on its hosts and only its gateway routes are withdrawn
+    pub async fn set_maintenance(
+        &self,
+        msg: Arc<Message>,
+    ) -> Result<types::ApiResult, anyhow::Error> {
+        log::debug!("Incoming message for 'WORKLOAD.maintenance'");
+        Ok(self
+            .process_request(
+                msg,
+                WorkloadState::Maintenance,
+                |request: types::MaintenanceRequest| async move {
+                    let workload_query = doc! { "_id":  request.workload_id.clone() };
+                    let workload = self
+                        .workload_collection
+                        .get_one_from(workload_query.clone())
+                        .await
+                        .for_workload(&request.workload_id)?
+                        .ok_or(anyhow!("Failed to locate workload"))
+                        .for_workload(&request.workload_id)?;
+
+                    let status = match (request.enabled, workload.status) {
+                        (true, _) => WorkloadStatus {
+                            id: Some(request.workload_id.clone()),
+                            host_device_id: None,
+                            desired: WorkloadState::Maintenance,
+                            actual: WorkloadState::Maintenance,
+                        },
+                        (
+                            false,
+                            Some(WorkloadStatus {
+                                actual: WorkloadState::Maintenance,
+                                ..
+                            }),
+                        ) => WorkloadStatus {
+                            id: Some(request.workload_id.clone()),
+                            host_device_id: None,
+                            desired: WorkloadState::Running,
+                            actual: WorkloadState::Running,
+                        },
+                        // Nothing to do when the workload is not in maintenance
+                        (false, Some(status)) => return Ok(types::ApiResult(status, None)),
+                        (false, None) => {
+                            return Err(anyhow!("Workload has not reported any status yet"))
+                                .for_workload(&request.workload_id)
+                        }
+                    };
+
+                    let updated_status_doc = doc! { "$set": { "status": bson::to_bson(&status)? } };
+                    self.workload_collection
+                        .update_one_within(
+                            workload_query,
+                            UpdateModifications::Document(updated_status_doc),
+                        )
+                        .await?;
+                    log::info!(
+                        "Successfully updated workload maintenance mode. MongodDB Workload ID={:?}, Enabled={}",
+                        request.workload_id,
+                        request.enabled
+                    );
+
+                    Ok(types::ApiResult(status, None))
+                },
+            )
+            .await)
+    }
+
+    // NB: Cordoned hosts keep their workloads, but are not assigned any new workload
+    pub async fn cordon_host(&self, msg: Arc<Message>) -> Result<types::ApiResult, anyhow::Error> {
+        log::debug!("Incoming message for 'WORKLOAD.orchestrator.cordon_host'");
+        Ok(self
+            .process_request(
+                msg,
+                WorkloadState::Unknown("Host cordon update".to_string()),
+                |request: types::CordonRequest| async move {
+                    self.set_host_cordoned(&request.host_id, request.cordoned)
+                        .await?;
+                    log::info!(
+                        "Successfully updated host cordon. MongodDB Host ID={:?}, Cordoned={}",
+                        request.host_id,
+                        request.cordoned
+                    );
+                    let state = WorkloadState::Unknown(format!(
+                        "Host cordoned={}. Host ID={}",
+                        request.cordoned, request.host_id
+                    ));
+                    Ok(types::ApiResult(
+                        WorkloadStatus {
+                            id: None,
+                            host_device_id: None,
+                            desired: state.clone(),
+                            actual: state,
+                        },
+                        None,
+                    ))
+                },
+            )
+            .await)
+    }
+
+    // Cordon the host and move each of its workloads to another eligible host
+    // NB: The instances on the drained host are only asked to be removed, so that they can be torn down once their replacements are installed
+    pub async fn drain_host(&self, msg: Arc<Message>) -> Result<types::ApiResult, anyhow::Error> {
+        log::debug!("Incoming message for 'WORKLOAD.orchestrator.drain_host'");
+        Ok(self.process_request(
+            msg,
+            WorkloadState::Assigned,
+            |host_id: schemas::MongoDbId| async move {
+                // 1. Stop assigning new workloads to the host
+                self.set_host_cordoned(&host_id, true).await?;
+
+                // 2. Find a replacement host for every workload instance on the host
+                let jobs_query = doc! {
+                    "host_id": host_id.clone(),
+                    "desired_state": { "$ne": bson::to_bson(&WorkloadState::Removed)? }
+                };
+                let mut replacement_host_ids = vec![];
+                for job in self.job_collection.get_many_from(jobs_query).await? {
+                    let workload_query = doc! { "_id":  job.workload_id.clone() };
+                    let workload = match self.workload_collection.get_one_from(workload_query.clone()).await? {
+                        Some(workload) => workload,
+                        None => continue,
+                    };
+
+                    let candidates: Vec<Host> = self
+                        .get_eligible_hosts(&workload)
+                        .await?
+                        .into_iter()
+                        .filter(|h| h._id.as_ref().is_some_and(|id| !workload.assigned_hosts.contains(id)))
+                        .collect();
+                    // NB: The placement constraints (eg: spreading across hosters) also apply to the hosts the workload keeps running on
+                    let remaining_host_ids: Vec<&String> = workload.assigned_hosts.iter().filter(|id| **id != host_id).collect();
+                    let remaining_hosts = self.host_collection.get_many_from(doc! { "_id": { "$in": remaining_host_ids } }).await?;
+                    let single_host_workload = Workload { min_hosts: 1, ..workload.clone() };
+                    let replacement = match self.scheduler.place_alongside(&single_host_workload, candidates, &remaining_hosts).into_iter().next() {
+                        Some(host) => host,
+                        None => {
+                            log::warn!("No eligible host to move workload to. Leaving it on the drained host. MongodDB Workload ID={:?}, MongodDB Host ID={:?}", job.workload_id, host_id);
+                            continue;
+                        }
+                    };
+                    let replacement_id = replacement._id.to_owned().unwrap();
+
+                    // 3. Swap the drained host for its replacement
+                    let assigned_hosts: Vec<String> = workload
+                        .assigned_hosts
+                        .iter()
+                        .map(|id| if *id == host_id { replacement_id.clone() } else { id.clone() })
+                        .collect();
+                    self.workload_collection.update_one_within(
+                        workload_query,
+                        UpdateModifications::Document(doc! { "$set": { "assigned_hosts": assigned_hosts } }),
+                    ).await?;
+                    let capacity = &workload.system_specs.capacity;
+                    self.host_collection.update_one_within(
+                        doc! { "_id": replacement_id.clone() },
+                        UpdateModifications::Document(doc! {
+                            "$push": { "assigned_workloads": job.workload_id.clone() },
+                            "$inc": Self::remaining_capacity_inc(capacity, -1)
+                        }),
+                    ).await?;
+                    self.host_collection.update_one_within(
+                        doc! { "_id": host_id.clone() },
+                        UpdateModifications::Document(doc! {
+                            "$pull": { "assigned_workloads": job.workload_id.clone() },
+                            "$inc": Self::remaining_capacity_inc(capacity, 1)
+                        }),
+                    ).await?;
+
+                    // 4. Start a new instance on the replacement, and retire the old one
+                    let replacement_job_id = self.job_collection.insert_one_into(Job {
+                        workload_id: job.workload_id.clone(),
+                        host_id: replacement_id.clone(),
+                        version: workload.version.clone(),
+                        ..Default::default()
+                    }).await?;
+                    self.record_job_event(
+                        &job.workload_id,
+                        Some(replacement_job_id),
+                        Some(replacement_id.clone()),
+                        JobEventSource::Orchestrator,
+                        WorkloadState::Assigned,
+                    )
+                    .await;
+                    self.job_collection.update_one_within(
+                        doc! { "_id": job._id },
+                        UpdateModifications::Document(doc! { "$set": { "desired_state": bson::to_bson(&WorkloadState::Removed)? } }),
+                    ).await?;
+                    log::info!(
+                        "Moved workload off drained host. MongodDB Workload ID={:?}, Drained Host ID={:?}, Replacement Host ID={:?}",
+                        job.workload_id, host_id, replacement_id
+                    );
+                    replacement_host_ids.push(replacement_id);
+                }
+
+                Ok(types::ApiResult(
+                    WorkloadStatus {
+                        id: None,
+                        host_device_id: None,
+                        desired: WorkloadState::Assigned,
+                        actual: WorkloadState::Assigned,
+                    },
+                    Some(replacement_host_ids),
+                ))
+            },
+        )
+        .await)
+    }
+
+    // Adjust the number of hosts of a workload with an autoscaling policy to its gateway traffic
+    // NB: Published periodically by the gateway
+    pub async fn handle_traffic_metrics(
+        &self,
+        msg: Arc<Message>,
+    ) -> Result<types::ApiResult, anyhow::Error> {
+        log::debug!("Incoming message for 'WORKLOAD.gateway_metrics'");
+        Ok(self.process_request(
+            msg,
+            WorkloadState::Running,
+            |metrics: types::TrafficMetrics| async move {
+                let workload_id = metrics.workload_id.clone();
+                let workload_query = doc! { "_id":  workload_id.clone() };
+                let workload = self.workload_collection.get_one_from(workload_query.clone()).await.for_workload(&workload_id)?
+                    .ok_or(anyhow!("Failed to locate workload")).for_workload(&workload_id)?;
+                let unchanged = types::ApiResult(
+                    WorkloadStatus {
+                        id: Some(workload_id.clone()),
+                        host_device_id: None,
+                        desired: WorkloadState::Running,
+                        actual: WorkloadState::Running,
+                    },
+                    None,
+                );
+                let Some(policy) = &workload.autoscaling else {
+                    return Ok(unchanged);
+                };
+
+                // NB: The traffic of a workload that is not running anywhere yet (eg: still being installed) says nothing about the hosts it needs
+                let running_jobs_query = doc! {
+                    "workload_id": workload_id.clone(),
+                    "current_state": {
+                        "$in": [bson::to_bson(&WorkloadState::Running)?, bson::to_bson(&WorkloadState::Healthy)?]
+                    }
+                };
+                if self.job_collection.get_one_from(running_jobs_query).await.for_workload(&workload_id)?.is_none() {
+                    log::debug!("Ignoring traffic metrics of a workload without any running instance. MongodDB Workload ID={:?}", workload_id);
+                    return Ok(unchanged);
+                }
+
+                let current_hosts = workload.assigned_hosts.len() as u16;
+                let desired_hosts = autoscale::desired_host_count(policy, current_hosts, metrics.requests_per_sec);
+                if desired_hosts == current_hosts {
+                    return Ok(unchanged);
+                }
+                log::info!(
+                    "Autoscaling workload. MongodDB Workload ID={:?}, Requests/s={}, Current Hosts={}, Desired Hosts={}",
+                    workload_id, metrics.requests_per_sec, current_hosts, desired_hosts
+                );
+
+                let added_host_ids = if desired_hosts > current_hosts {
+                    // NB: Scaling out counts against the quota of the developer
+                    let scaled_workload = Workload { min_hosts: desired_hosts, ..workload.clone() };
+                    self.check_developer_quota(&scaled_workload).await.for_workload(&workload_id)?;
+                    self.scale_out(&workload, desired_hosts - current_hosts).await.for_workload(&workload_id)?
+                } else {
+                    self.scale_in(&workload, current_hosts - desired_hosts).await.for_workload(&workload_id)?;
+                    vec![]
+                };
+                // NB: There may not be enough eligible hosts to scale out to, so the hosts actually placed are persisted rather than the desired hosts
+                // ...as otherwise the missing hosts would be taken for preempted hosts, and rescheduled without regard to the traffic
+                let achieved_hosts = if desired_hosts > current_hosts {
+                    current_hosts + added_host_ids.len() as u16
+                } else {
+                    desired_hosts
+                };
+                self.workload_collection.update_one_within(
+                    workload_query,
+                    UpdateModifications::Document(doc! { "$set": { "min_hosts": achieved_hosts as i32 } }),
+                ).await.for_workload(&workload_id)?;
+
+                // NB: Only the added hosts are sent the workload. The removed instances are torn down through their jobs.
+                Ok(types::ApiResult(
+                    WorkloadStatus {
+                        id: Some(workload_id),
+                        host_device_id: None,
+                        desired: WorkloadState::Running,
+                        actual: WorkloadState::Assigned,
+                    },
+                    Some(added_host_ids),
+                ))
+            },
         )
         .await)
     }
@@ -180,7 +552,7 @@ impl WorkloadApi {
                 // 0. Fail Safe: exit early if the workload provided does not include an `_id` field
                 let workload_id = if let Some(id) = workload.clone()._id { id } else {
                     let err_msg = format!("No `_id` found for workload.  Unable to proceed assigning a host. Workload={:?}", workload);
-                    return Err(anyhow!(err_msg));
+                    return Err(anyhow!(err_msg).into());
                 };
 
                 // 1. Perform sanity check to ensure workload is not already assigned to a host
@@ -191,71 +563,117 @@ impl WorkloadApi {
                     return Ok(types::ApiResult(
                     WorkloadStatus {
                         id: Some(workload_id),
+                        host_device_id: None,
                         desired: WorkloadState::Assigned,
                         actual: WorkloadState::Assigned,
                     },
                     Some(workload.assigned_hosts)));
                 }
 
-                // 2. Otherwise call mongodb to get host collection to get hosts that meet the capacity requirements
-                let host_filter = doc! {
-                    "remaining_capacity.cores": { "$gte": workload.system_specs.capacity.cores },
-                    "remaining_capacity.memory": { "$gte": workload.system_specs.capacity.memory },
-                    "remaining_capacity.disk": { "$gte": workload.system_specs.capacity.disk }
-                };
-                let eligible_hosts = self.host_collection.get_many_from(host_filter).await? ;
+                // 2. Hold off on assigning a host until every workload dependency reports that it is running
+                let pending_dependencies = self.get_pending_dependencies(&workload).await.for_workload(&workload_id)?;
+                if !pending_dependencies.is_empty() {
+                    log::info!("Workload dependencies are not running yet. Deferring host assignment. MongodDB Workload ID={:?}, Pending Dependency IDs={:?}", workload_id, pending_dependencies);
+                    return Ok(types::ApiResult(
+                    WorkloadStatus {
+                        id: Some(workload_id),
+                        host_device_id: None,
+                        desired: WorkloadState::Assigned,
+                        actual: WorkloadState::Pending,
+                    },
+                    None));
+                }
+
+                // 3. Otherwise call mongodb to get host collection to get hosts of the workload's network that meet the capacity requirements
+                let eligible_hosts = self.get_eligible_hosts(&workload).await.for_workload(&workload_id)?;
                 log::debug!("Eligible hosts for new workload. MongodDB Host IDs={:?}", eligible_hosts);
 
-                // 3. Randomly choose host/node
-                let host = match eligible_hosts.choose(&mut rand::thread_rng()) {
-                    Some(h) => h,
-                    None => {
-                        // todo: Try to get another host up to 5 times, if fails thereafter, return error
-                        let err_msg = format!("Failed to locate an eligible host to support the required workload capacity. Workload={:?}", workload);
-                        return Err(anyhow!(err_msg));
-                    }
-                };
+                // 4. Pick the best scoring hosts/nodes
+                let mut hosts = self.scheduler.place(&workload, eligible_hosts);
+                if hosts.is_empty() {
+                    // ...or make room on hosts by preempting workloads of a lower priority
+                    hosts = self.preempt_for(&workload).await.for_workload(&workload_id)?;
+                }
+                if hosts.is_empty() {
+                    // todo: Try to get another host up to 5 times, if fails thereafter, return error
+                    let err_msg = format!("Failed to locate an eligible host to support the required workload capacity. Workload={:?}", workload);
+                    return Err(anyhow!(err_msg)).for_workload(&workload_id);
+                }
+                if hosts.len() < workload.min_hosts as usize {
+                    log::warn!("Not enough eligible hosts to meet the workload's minimum host count. MongodDB Workload ID={:?}, Min Hosts={}, Eligible Hosts={}", workload_id, workload.min_hosts, hosts.len());
+                }
 
                 // Note: The `_id` is an option because it is only generated upon the intial insertion of a record in
                 // a mongodb collection. This also means that whenever a record is fetched from mongodb, it must have the `_id` feild.
                 // Using `unwrap` is therefore safe.
-                let host_id = host._id.to_owned().unwrap();
+                let host_ids: Vec<schemas::MongoDbId> = hosts.iter().map(|h| h._id.to_owned().unwrap()).collect();
 
-                // 4. Update the Workload Collection with the assigned Host ID
+                // 5. Update the Workload Collection with the assigned Host IDs
                 let workload_query = doc! { "_id":  workload_id.clone() };
                 let updated_workload = &Workload {
-                    assigned_hosts: vec![host_id],
+                    assigned_hosts: host_ids,
                     ..workload.clone()
                 };
                 let updated_workload_doc = to_document(updated_workload)?;
-                let updated_workload_result = self.workload_collection.update_one_within(workload_query, UpdateModifications::Document(updated_workload_doc)).await?;
+                let updated_workload_result = self.workload_collection.update_one_within(workload_query, UpdateModifications::Document(updated_workload_doc)).await.for_workload(&workload_id)?;
                 log::trace!(
                     "Successfully added new workload into the Workload Collection. MongodDB Workload ID={:?}",
                     updated_workload_result
                 );
 
-                // 5. Update the Host Collection with the assigned Workload ID
-                let host_query = doc! { "_id":  host.clone()._id };
-                let updated_host_doc =  to_document(&Host {
-                    assigned_workloads: vec![workload_id.clone()],
-                    ..host.to_owned()
-                })?;
-                let updated_host_result = self.host_collection.update_one_within(host_query, UpdateModifications::Document(updated_host_doc)).await?;
+                // 6. Update the Host Collection with the assigned Workload ID, and the capacity it takes up
+                // NB: The workload is pushed onto the workloads already assigned to the hosts (rather than rewriting the hosts),
+                // ...so that concurrent updates of the hosts (eg: the evictions of a preemption) are not overwritten
+                for host in hosts {
+                    let host_query = doc! { "_id":  host._id };
+                    let updated_host_doc = doc! {
+                        "$push": { "assigned_workloads": workload_id.clone() },
+                        "$inc": Self::remaining_capacity_inc(&workload.system_specs.capacity, -1)
+                    };
+                    let updated_host_result = self.host_collection.update_one_within(host_query, UpdateModifications::Document(updated_host_doc)).await.for_workload(&workload_id)?;
+                    log::trace!(
+                        "Successfully added new workload into the Workload Collection. MongodDB Host ID={:?}",
+                        updated_host_result
+                    );
+                }
+
+                // 7. Track the instance of the workload on each of its hosts
+                let jobs = updated_workload.assigned_hosts.iter().map(|host_id| Job {
+                    workload_id: workload_id.clone(),
+                    host_id: host_id.clone(),
+                    version: workload.version.clone(),
+                    ..Default::default()
+                }).collect();
+                let job_ids = self.job_collection.insert_many_into(jobs).await.for_workload(&workload_id)?;
                 log::trace!(
-                    "Successfully added new workload into the Workload Collection. MongodDB Host ID={:?}",
-                    updated_host_result
+                    "Successfully added new jobs into the Job Collection. MongodDB Job IDs={:?}",
+                    job_ids
                 );
+                // NB: The job IDs are returned in the order of the jobs, ie: of the assigned hosts
+                for (job_id, host_id) in job_ids
+                    .into_iter()
+                    .zip(updated_workload.assigned_hosts.iter())
+                {
+                    self.record_job_event(
+                        &workload_id,
+                        Some(job_id),
+                        Some(host_id.clone()),
+                        JobEventSource::Orchestrator,
+                        WorkloadState::Assigned,
+                    )
+                    .await;
+                }
 
                 Ok(types::ApiResult(
                     WorkloadStatus {
                         id: Some(workload_id),
+                        host_device_id: None,
                         desired: WorkloadState::Assigned,
                         actual: WorkloadState::Assigned,
                     },
                     Some(updated_workload.assigned_hosts.to_owned())
                 ))
         },
-            WorkloadState::Error,
         )
         .await)
     }
@@ -267,20 +685,43 @@ impl WorkloadApi {
         msg: Arc<Message>,
     ) -> Result<types::ApiResult, anyhow::Error> {
         log::debug!("Incoming message for 'WORKLOAD.update'");
+        Ok(self
+            .process_request(
+                msg,
+                WorkloadState::Running,
+                |workload: schemas::Workload| async move {
+                    log::trace!("Updated workload to roll out. Workload={:#?}", workload);
+                    let workload_id = workload._id.clone().ok_or(anyhow!(
+                        "No `_id` found for workload. Unable to roll out the update. Workload={:?}",
+                        workload
+                    ))?;
 
-        let payload_buf = msg.payload.to_vec();
-        let workload: schemas::Workload = serde_json::from_slice(&payload_buf)?;
-        log::trace!("New workload to assign. Workload={:#?}", workload);
-
-        // TODO: ...handle the use case for the update entry change stream
-
-        let success_status = WorkloadStatus {
-            id: workload._id,
-            desired: WorkloadState::Running,
-            actual: WorkloadState::Running,
-        };
+                    // Send the update to the next batch of hosts allowed by the rollout strategy of the workload
+                    let host_ids = self.roll_out_next_batch(&workload).await?;
+                    if host_ids.is_empty() {
+                        return Ok(types::ApiResult(
+                            WorkloadStatus {
+                                id: Some(workload_id),
+                                host_device_id: None,
+                                desired: WorkloadState::Running,
+                                actual: WorkloadState::Running,
+                            },
+                            None,
+                        ));
+                    }
 
-        Ok(types::ApiResult(success_status, None))
+                    Ok(types::ApiResult(
+                        WorkloadStatus {
+                            id: Some(workload_id),
+                            host_device_id: None,
+                            desired: WorkloadState::Running,
+                            actual: WorkloadState::Pending,
+                        },
+                        Some(host_ids),
+                    ))
+                },
+            )
+            .await)
     }
 
     // Zeeshan to take a look:
@@ -299,6 +740,7 @@ impl WorkloadApi {
 
         let success_status = WorkloadStatus {
             id: workload._id,
+            host_device_id: None,
             desired: WorkloadState::Removed,
             actual: WorkloadState::Removed,
         };
@@ -317,11 +759,438 @@ impl WorkloadApi {
         let workload_status: WorkloadStatus = serde_json::from_slice(&payload_buf)?;
         log::trace!("Workload status to update. Status={:?}", workload_status);
 
-        // TODO: ...handle the use case for the workload status update
+        // Persist the latest status on the workload record
+        // NB: Workloads that depend on this workload are only assigned once it reports `Running` (or `Healthy`)
+        // NB: A workload in maintenance keeps its status when its hosts report it as running, so that it stays withdrawn from the gateway routes
+        if let Some(workload_id) = workload_status.id.clone() {
+            let reporting_host = self.get_reporting_host(&workload_status).await?;
+
+            // NB: The states reported by a known host for its instance are recorded as transitions of its job (see `update_job_state` and `schedule_retry`).
+            // ...Other reports are recorded for the workload as a whole, but only when they change its state, as hosts report their workloads periodically.
+            let tracked_on_job = reporting_host.is_some()
+                && matches!(
+                    workload_status.actual,
+                    WorkloadState::Running
+                        | WorkloadState::Healthy
+                        | WorkloadState::Unhealthy(_)
+                        | WorkloadState::Error(_)
+                        | WorkloadState::Uninstalled
+                );
+            if !tracked_on_job {
+                let previous_state = self
+                    .workload_collection
+                    .get_one_from(doc! { "_id":  workload_id.clone() })
+                    .await?
+                    .and_then(|workload| workload.status)
+                    .map(|status| status.actual);
+                if previous_state.as_ref() != Some(&workload_status.actual) {
+                    self.record_job_event(
+                        &workload_id,
+                        None,
+                        reporting_host.as_ref().and_then(|host| host._id.clone()),
+                        JobEventSource::Host,
+                        workload_status.actual.clone(),
+                    )
+                    .await;
+                }
+            }
+
+            // A host acknowledging the uninstall of its instance completes the removal of the instance
+            // NB: The workload keeps its status, as its other instances (if any) are not affected
+            if let (WorkloadState::Uninstalled, Some(host)) =
+                (&workload_status.actual, &reporting_host)
+            {
+                self.record_uninstalled_job(&workload_id, host).await?;
+                return Ok(types::ApiResult(workload_status, None));
+            }
+
+            let workload_query = match workload_status.actual {
+                WorkloadState::Running | WorkloadState::Healthy | WorkloadState::Unhealthy(_) => {
+                    doc! {
+                        "_id":  workload_id.clone(),
+                        "status.actual": { "$ne": bson::to_bson(&WorkloadState::Maintenance)? }
+                    }
+                }
+                _ => doc! { "_id":  workload_id.clone() },
+            };
+            let updated_status_doc =
+                doc! { "$set": { "status": bson::to_bson(&workload_status)? } };
+            self.workload_collection
+                .update_one_within(
+                    workload_query,
+                    UpdateModifications::Document(updated_status_doc),
+                )
+                .await?;
+
+            // A workload failing on a host counts against the trust score of that host, and is retried according to its retry policy
+            if let WorkloadState::Error(err) = &workload_status.actual {
+                match &reporting_host {
+                    Some(host) => {
+                        self.record_failed_install(host).await?;
+                        self.schedule_retry(&workload_id, host, err).await?;
+                    }
+                    None => log::warn!(
+                        "Workload error was not reported by a known host. Leaving host trust and retries unchanged. MongodDB Workload ID={:?}, Host Device ID={:?}",
+                        workload_id,
+                        workload_status.host_device_id
+                    ),
+                }
+            }
+
+            // A running (or healthy) report from a host lets the rollout of an update continue with the next batch of hosts
+            // NB: Unhealthy instances are kept out of the gateway routes until they pass their health check again
+            if let (
+                WorkloadState::Running | WorkloadState::Healthy | WorkloadState::Unhealthy(_),
+                Some(host),
+            ) = (&workload_status.actual, &reporting_host)
+            {
+                let host_ids = self
+                    .update_job_state(&workload_id, host, &workload_status.actual)
+                    .await?;
+                if !host_ids.is_empty() {
+                    return Ok(types::ApiResult(workload_status, Some(host_ids)));
+                }
+            }
+        }
 
         Ok(types::ApiResult(workload_status, None))
     }
 
+    /// Re-dispatch the failed installs whose backoff has elapsed.
+    /// Returns one result per workload, tagged with the hosts to send the workload to again.
+    /// NB: This is meant to be called periodically by the orchestrator service, which is not part of this tree yet,
+    /// so failed installs are not retried until it is.
+    pub async fn retry_due_jobs(&self) -> Result<Vec<types::ApiResult>> {
+        let now = chrono::Utc::now().timestamp();
+        let due_jobs_query = doc! { "retry_at": { "$lte": now } };
+        let mut due_hosts: HashMap<schemas::MongoDbId, Vec<String>> = HashMap::new();
+        for job in self.job_collection.get_many_from(due_jobs_query).await? {
+            let job_query = doc! { "_id": job._id.clone() };
+            let retried_job_doc = doc! {
+                "$set": {
+                    "current_state": bson::to_bson(&WorkloadState::Pending)?,
+                    "retry_at": Bson::Null
+                }
+            };
+            self.job_collection
+                .update_one_within(job_query, UpdateModifications::Document(retried_job_doc))
+                .await?;
+            self.record_job_event(
+                &job.workload_id,
+                job._id,
+                Some(job.host_id.clone()),
+                JobEventSource::Orchestrator,
+                WorkloadState::Pending,
+            )
+            .await;
+            due_hosts
+                .entry(job.workload_id)
+                .or_default()
+                .push(job.host_id);
+        }
+
+        let mut results = vec![];
+        for (workload_id, host_ids) in due_hosts {
+            log::info!(
+                "Retrying workload install. MongodDB Workload ID={:?}, Host IDs={:?}",
+                workload_id,
+                host_ids
+            );
+            results.push(types::ApiResult(
+                WorkloadStatus {
+                    id: Some(workload_id),
+                    host_device_id: None,
+                    desired: WorkloadState::Running,
+                    actual: WorkloadState::Pending,
+                },
+                Some(host_ids),
+            ));
+        }
+        Ok(results)
+    }
+
+    /// Compare the desired and actual state of every job, re-dispatch the commands of the jobs that have
+    /// been in transition for too long, and flag the jobs that are stuck.
+    /// Returns one result per workload and desired state, tagged with the hosts to send the command to again.
+    /// NB: This is meant to be called periodically by the orchestrator service, which is not part of this tree yet,
+    /// so lost commands are not reissued until it is.
+    pub async fn reconcile_jobs(&self) -> Result<Vec<types::ApiResult>> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut jobs_by_workload: HashMap<schemas::MongoDbId, Vec<Job>> = HashMap::new();
+        for job in self.job_collection.get_many_from(doc! {}).await? {
+            jobs_by_workload
+                .entry(job.workload_id.clone())
+                .or_default()
+                .push(job);
+        }
+
+        let mut reissued_hosts: HashMap<
+            (schemas::MongoDbId, String),
+            (WorkloadState, Vec<String>),
+        > = HashMap::new();
+        for (workload_id, jobs) in jobs_by_workload {
+            let events_query = doc! { "workload_id": workload_id.clone() };
+            let events = self
+                .job_event_collection
+                .get_many_from(events_query)
+                .await?;
+
+            for job in jobs {
+                // NB: Events that are not tied to a job (eg: reported without a known host) count for every job of the workload
+                let last_transition_at = events
+                    .iter()
+                    .filter(|e| e.job_id.is_none() || e.job_id == job._id)
+                    .map(|e| e.created_at)
+                    .max();
+                let action =
+                    reconcile::next_action(&self.reconciler, &job, last_transition_at, now);
+                match action {
+                    reconcile::JobAction::Reissue if !job.stuck => {
+                        self.set_job_reissued(&job, now).await?;
+                        let key = (workload_id.clone(), format!("{:?}", job.desired_state));
+                        reissued_hosts
+                            .entry(key)
+                            .or_insert_with(|| (job.desired_state.clone(), vec![]))
+                            .1
+                            .push(job.host_id.clone());
+                    }
+                    reconcile::JobAction::Stuck if !job.stuck => {
+                        log::warn!(
+                            "Job is stuck in transition. MongodDB Job ID={:?}, Workload ID={:?}, Host ID={:?}, Desired State={:?}, Current State={:?}",
+                            job._id, workload_id, job.host_id, job.desired_state, job.current_state
+                        );
+                        self.set_job_stuck(&job, true).await?;
+                    }
+                    reconcile::JobAction::Converged if job.stuck => {
+                        self.set_job_stuck(&job, false).await?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut results = vec![];
+        for ((workload_id, _), (desired_state, host_ids)) in reissued_hosts {
+            log::info!(
+                "Reissuing workload command. MongodDB Workload ID={:?}, Desired State={:?}, Host IDs={:?}",
+                workload_id,
+                desired_state,
+                host_ids
+            );
+            results.push(types::ApiResult(
+                WorkloadStatus {
+                    id: Some(workload_id),
+                    host_device_id: None,
+                    desired: desired_state,
+                    actual: WorkloadState::Pending,
+                },
+                Some(host_ids),
+            ));
+        }
+        Ok(results)
+    }
+
+    /// Assign new hosts to the workloads that were preempted from some of their hosts, in place of the hosts they lost.
+    /// Returns one result per workload, tagged with the hosts to send the workload to.
+    /// NB: This is meant to be called periodically by the orchestrator service (as there may not be any room for the workloads right away),
+    /// which is not part of this tree yet, so preempted workloads are not rescheduled until it is.
+    pub async fn reschedule_preempted_workloads(&self) -> Result<Vec<types::ApiResult>> {
+        // NB: The preempted instances are marked as uninstalled once their host acknowledges their removal
+        let preempted_jobs_query = doc! {
+            "current_state": {
+                "$in": [
+                    bson::to_bson(&WorkloadState::Preempted)?,
+                    bson::to_bson(&WorkloadState::Uninstalled)?
+                ]
+            }
+        };
+        let mut preempted_ids: Vec<schemas::MongoDbId> = self
+            .job_collection
+            .get_many_from(preempted_jobs_query)
+            .await?
+            .into_iter()
+            .map(|job| job.workload_id)
+            .collect();
+        preempted_ids.sort();
+        preempted_ids.dedup();
+
+        let workloads_query = doc! { "_id": { "$in": preempted_ids } };
+        let mut results = vec![];
+        for workload in self
+            .workload_collection
+            .get_many_from(workloads_query)
+            .await?
+        {
+            let missing_hosts = workload
+                .min_hosts
+                .saturating_sub(workload.assigned_hosts.len() as u16);
+            if missing_hosts == 0 {
+                continue;
+            }
+            let added_host_ids = self.scale_out(&workload, missing_hosts).await?;
+            if added_host_ids.is_empty() {
+                continue;
+            }
+            log::info!(
+                "Rescheduled preempted workload. MongodDB Workload ID={:?}, Added Host IDs={:?}",
+                workload._id,
+                added_host_ids
+            );
+            results.push(types::ApiResult(
+                WorkloadStatus {
+                    id: workload._id,
+                    host_device_id: None,
+                    desired: WorkloadState::Running,
+                    actual: WorkloadState::Assigned,
+                },
+                Some(added_host_ids),
+            ));
+        }
+        Ok(results)
+    }
+
+    // Lists the state transitions of the instances of a workload, oldest first
+    pub async fn get_job_events(
+        &self,
+        msg: Arc<Message>,
+    ) -> Result<types::JobEventsResult, anyhow::Error> {
+        log::debug!("Incoming message for 'WORKLOAD.events'");
+        let workload_id: schemas::MongoDbId = serde_json::from_slice(&msg.payload)?;
+        let events_query = doc! { "workload_id": workload_id.clone() };
+        let mut events = self
+            .job_event_collection
+            .get_many_from(events_query)
+            .await?;
+        events.sort_by_key(|event| event.created_at);
+        Ok(types::JobEventsResult {
+            workload_id,
+            events,
+        })
+    }
+
+    // Record whether the gateway currently skips a host, so that the host is left out of the routing table meanwhile
+    // NB: Published by the gateway whenever the circuit of a host opens or closes
+    pub async fn handle_host_health_transition(
+        &self,
+        msg: Arc<Message>,
+    ) -> Result<types::ApiResult, anyhow::Error> {
+        log::debug!("Incoming message for 'WORKLOAD.gateway_host_health'");
+        Ok(self
+            .process_request(
+                msg,
+                WorkloadState::Unknown("Host upstream health update".to_string()),
+                |transition: types::HostHealthTransition| async move {
+                    let host_query = doc! { "_id":  transition.host_id.clone() };
+                    let updated_host_doc = doc! { "$set": { "circuit_open": !transition.healthy } };
+                    self.host_collection
+                        .update_one_within(
+                            host_query,
+                            UpdateModifications::Document(updated_host_doc),
+                        )
+                        .await?;
+                    if transition.healthy {
+                        log::info!(
+                            "Gateway closed the circuit of host. MongodDB Host ID={:?}",
+                            transition.host_id
+                        );
+                    } else {
+                        log::warn!(
+                            "Gateway opened the circuit of host. MongodDB Host ID={:?}, Reason={:?}",
+                            transition.host_id,
+                            transition.reason
+                        );
+                    }
+                    let state = WorkloadState::Unknown(format!(
+                        "Host upstream healthy={}. Host ID={}",
+                        transition.healthy, transition.host_id
+                    ));
+                    Ok(types::ApiResult(
+                        WorkloadStatus {
+                            id: None,
+                            host_device_id: None,
+                            desired: state.clone(),
+                            actual: state,
+                        },
+                        None,
+                    ))
+                },
+            )
+            .await)
+    }
+
+    // Lists the hosts each workload can be reached on, so that the gateway can route its requests (and fail over) to them
+    // NB: Only the instances that report running are listed, the ones that pass their health check first (unhealthy instances are left out)
+    // NB: Hosts whose circuit is open in the gateway, and workloads in maintenance, are left out
+    pub async fn get_routing_table(
+        &self,
+        msg: Arc<Message>,
+    ) -> Result<types::RoutingTable, anyhow::Error> {
+        log::debug!("Incoming message for 'WORKLOAD.routes'");
+        let request: types::RoutesRequest = serde_json::from_slice(&msg.payload)?;
+
+        let mut workloads_query = doc! {
+            "status.actual": { "$ne": bson::to_bson(&WorkloadState::Maintenance)? }
+        };
+        if !request.workload_ids.is_empty() {
+            workloads_query.insert("_id", doc! { "$in": request.workload_ids });
+        }
+        if let Some(domain) = request.domain {
+            workloads_query.insert("domains", domain.to_lowercase());
+        }
+        let workloads = self
+            .workload_collection
+            .get_many_from(workloads_query)
+            .await?;
+        let workload_ids: Vec<schemas::MongoDbId> =
+            workloads.iter().filter_map(|w| w._id.clone()).collect();
+
+        let jobs_query = doc! {
+            "workload_id": { "$in": workload_ids },
+            "desired_state": bson::to_bson(&WorkloadState::Running)?,
+            "current_state": {
+                "$in": [
+                    bson::to_bson(&WorkloadState::Healthy)?,
+                    bson::to_bson(&WorkloadState::Running)?
+                ]
+            }
+        };
+        let open_circuit_host_ids: Vec<schemas::MongoDbId> = self
+            .host_collection
+            .get_many_from(doc! { "circuit_open": true })
+            .await?
+            .into_iter()
+            .filter_map(|h| h._id)
+            .collect();
+        let mut jobs: Vec<Job> = self
+            .job_collection
+            .get_many_from(jobs_query)
+            .await?
+            .into_iter()
+            .filter(|job| !open_circuit_host_ids.contains(&job.host_id))
+            .collect();
+        jobs.sort_by_key(|job| job.current_state != WorkloadState::Healthy);
+
+        let mut routes: Vec<types::WorkloadRoute> = vec![];
+        for job in jobs {
+            match routes.iter_mut().find(|r| r.workload_id == job.workload_id) {
+                Some(route) => route.hosts.push(job.host_id),
+                None => {
+                    let domains = workloads
+                        .iter()
+                        .find(|w| w._id.as_ref() == Some(&job.workload_id))
+                        .map(|w| w.domains.clone())
+                        .unwrap_or_default();
+                    routes.push(types::WorkloadRoute {
+                        workload_id: job.workload_id,
+                        domains,
+                        hosts: vec![job.host_id],
+                    })
+                }
+            }
+        }
+        Ok(types::RoutingTable { routes })
+    }
+
     /*******************************   For Host Agent   *********************************/
     pub async fn start_workload(
         &self,
@@ -332,13 +1201,30 @@ impl WorkloadApi {
         let payload_buf = msg.payload.to_vec();
         let workload = serde_json::from_slice::<schemas::Workload>(&payload_buf)?;
 
+        let command_id = ledger::CommandLedger::command_id(&msg);
+        if let Some(result) = self.skip_duplicate_command(&command_id, workload._id.clone()) {
+            return Ok(result);
+        }
+
         // TODO: Talk through with Stefan
         // 1. Connect to interface for Nix and instruct systemd to install workload...
         // eg: nix_install_with(workload)
 
-        // 2. Respond to endpoint request
+        // 2. Start checking that the workload is actually serving
+        if let (Some(workload_id), Some(health_check)) = (&workload._id, &workload.health_check) {
+            self.health_monitor
+                .register(workload_id.clone(), health_check.clone());
+        }
+
+        // 3. Remember the command, so that a redelivery of it is not run again
+        if let Some(command_id) = command_id {
+            self.command_ledger.record(command_id).await?;
+        }
+
+        // 4. Respond to endpoint request
         let status = WorkloadStatus {
             id: workload._id,
+            host_device_id: self.host_device_id.clone(),
             desired: WorkloadState::Running,
             actual: WorkloadState::Unknown("..".to_string()),
         };
@@ -354,15 +1240,29 @@ impl WorkloadApi {
         let payload_buf = msg.payload.to_vec();
         let workload_id = serde_json::from_slice::<String>(&payload_buf)?;
 
+        let command_id = ledger::CommandLedger::command_id(&msg);
+        if let Some(result) = self.skip_duplicate_command(&command_id, Some(workload_id.clone())) {
+            return Ok(result);
+        }
+
         // TODO: Talk through with Stefan
         // 1. Connect to interface for Nix and instruct systemd to UNinstall workload...
         // nix_uninstall_with(workload_id)
 
-        // 2. Respond to endpoint request
+        // 2. Stop checking the health of the workload
+        self.health_monitor.unregister(&workload_id);
+
+        // 3. Remember the command, so that a redelivery of it is not run again
+        if let Some(command_id) = command_id {
+            self.command_ledger.record(command_id).await?;
+        }
+
+        // 4. Respond to endpoint request
         let status = WorkloadStatus {
             id: Some(workload_id),
+            host_device_id: self.host_device_id.clone(),
             desired: WorkloadState::Uninstalled,
-            actual: WorkloadState::Unknown("..".to_string()),
+            actual: WorkloadState::Uninstalled,
         };
         Ok(types::ApiResult(status, None))
     }
@@ -387,6 +1287,27 @@ impl WorkloadApi {
         Ok(types::ApiResult(workload_status, None))
     }
 
+    // Respond with the logs of a workload installed on the host (either the last lines, or the lines within a time range)
+    pub async fn send_workload_logs(
+        &self,
+        msg: Arc<Message>,
+    ) -> Result<types::LogsResult, anyhow::Error> {
+        log::debug!("Incoming message for 'WORKLOAD.logs' : {:?}", msg);
+
+        let request = serde_json::from_slice::<types::LogsRequest>(&msg.payload)?;
+        if !self.is_assigned_to_host(&request.workload_id).await? {
+            return Err(anyhow!(
+                "Workload is not installed on this host. MongodDB Workload ID={:?}",
+                request.workload_id
+            ));
+        }
+        let lines = logs::read_logs(&request).await?;
+        Ok(types::LogsResult {
+            workload_id: request.workload_id,
+            lines,
+        })
+    }
+
     /*******************************  Helper Fns  *********************************/
     // Helper function to initialize mongodb collections
     async fn init_collection<T>(
@@ -399,47 +1320,785 @@ impl WorkloadApi {
         Ok(MongoCollection::<T>::new(client, schemas::DATABASE_NAME, collection_name).await?)
     }
 
+    // Helper function to send an update to the next batch of hosts of a workload, according to its rollout strategy
+    // Returns the IDs of the hosts the update should be sent to
+    async fn roll_out_next_batch(&self, workload: &Workload) -> Result<Vec<String>> {
+        let jobs_query = doc! {
+            "workload_id": workload._id.clone(),
+            "desired_state": { "$ne": bson::to_bson(&WorkloadState::Removed)? }
+        };
+        let jobs = self.job_collection.get_many_from(jobs_query).await?;
+        let (updated, remaining): (Vec<Job>, Vec<Job>) = jobs
+            .into_iter()
+            .partition(|job| job.version == workload.version);
+        let has_health_check = workload.health_check.is_some();
+        let in_flight = updated
+            .iter()
+            .filter(|job| !rollout::is_up(has_health_check, &job.current_state))
+            .count();
+        let progress = rollout::RolloutProgress {
+            updated: updated.len(),
+            in_flight,
+            remaining: remaining.len(),
+        };
+        let batch_size = rollout::next_batch_size(&workload.rollout_strategy, progress);
+        log::debug!(
+            "Workload rollout progress. MongodDB Workload ID={:?}, Progress={:?}, Batch Size={}",
+            workload._id,
+            progress,
+            batch_size
+        );
+
+        let mut host_ids = vec![];
+        for job in remaining.into_iter().take(batch_size) {
+            let job_query = doc! { "_id": job._id.clone() };
+            let updated_job_doc = doc! {
+                "$set": {
+                    "version": workload.version.clone(),
+                    "current_state": bson::to_bson(&WorkloadState::Pending)?
+                }
+            };
+            self.job_collection
+                .update_one_within(job_query, UpdateModifications::Document(updated_job_doc))
+                .await?;
+            self.record_job_event(
+                &job.workload_id,
+                job._id,
+                Some(job.host_id.clone()),
+                JobEventSource::Orchestrator,
+                WorkloadState::Pending,
+            )
+            .await;
+            host_ids.push(job.host_id);
+        }
+        Ok(host_ids)
+    }
+
+    // Helper function to persist the state reported by a host for its instance of a workload, and continue the rollout of the workload
+    // NB: Only the instance of the reporting host is updated, so that a rollout never advances before the hosts of the current batch report
+    // NB: A `Running` report does not override the result of the health check of an instance
+    async fn update_job_state(
+        &self,
+        workload_id: &schemas::MongoDbId,
+        host: &Host,
+        reported_state: &WorkloadState,
+    ) -> Result<Vec<String>> {
+        let workload_query = doc! { "_id":  workload_id.clone() };
+        let workload = match self
+            .workload_collection
+            .get_one_from(workload_query)
+            .await?
+        {
+            Some(workload) => workload,
+            None => return Ok(vec![]),
+        };
+
+        let jobs_query = doc! {
+            "workload_id": workload_id.clone(),
+            "host_id": host._id.clone(),
+            "desired_state": { "$ne": bson::to_bson(&WorkloadState::Removed)? }
+        };
+        for job in self.job_collection.get_many_from(jobs_query).await? {
+            let updatable = match job.current_state {
+                WorkloadState::Assigned | WorkloadState::Pending | WorkloadState::Installed => true,
+                WorkloadState::Running | WorkloadState::Healthy | WorkloadState::Unhealthy(_) => {
+                    reported_state != &WorkloadState::Running
+                }
+                _ => false,
+            };
+            if !updatable || job.current_state == *reported_state {
+                continue;
+            }
+
+            let job_query = doc! { "_id": job._id.clone() };
+            let updated_job_doc =
+                doc! { "$set": { "current_state": bson::to_bson(reported_state)? } };
+            self.job_collection
+                .update_one_within(job_query, UpdateModifications::Document(updated_job_doc))
+                .await?;
+            self.record_job_event(
+                &job.workload_id,
+                job._id,
+                Some(job.host_id),
+                JobEventSource::Host,
+                reported_state.clone(),
+            )
+            .await;
+        }
+
+        if !rollout::is_up(workload.health_check.is_some(), reported_state) {
+            return Ok(vec![]);
+        }
+        self.roll_out_next_batch(&workload).await
+    }
+
+    // Helper function to mark the instances of a workload that were being removed from the reporting host as uninstalled
+    async fn record_uninstalled_job(
+        &self,
+        workload_id: &schemas::MongoDbId,
+        host: &Host,
+    ) -> Result<()> {
+        let jobs_query = doc! {
+            "workload_id": workload_id.clone(),
+            "host_id": host._id.clone(),
+            "desired_state": bson::to_bson(&WorkloadState::Removed)?,
+            "current_state": { "$ne": bson::to_bson(&WorkloadState::Uninstalled)? }
+        };
+        let uninstalled_job_doc =
+            doc! { "$set": { "current_state": bson::to_bson(&WorkloadState::Uninstalled)? } };
+        for job in self.job_collection.get_many_from(jobs_query).await? {
+            self.job_collection
+                .update_one_within(
+                    doc! { "_id": job._id.clone() },
+                    UpdateModifications::Document(uninstalled_job_doc.clone()),
+                )
+                .await?;
+            self.record_job_event(
+                &job.workload_id,
+                job._id,
+                Some(job.host_id),
+                JobEventSource::Host,
+                WorkloadState::Uninstalled,
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    // Helper function to record a failed install attempt on the in-flight instance of a workload on the reporting host, and schedule its retry
+    // NB: The failed attempts of the other hosts still count towards the failure budget of the workload
+    async fn schedule_retry(
+        &self,
+        workload_id: &schemas::MongoDbId,
+        host: &Host,
+        err: &str,
+    ) -> Result<()> {
+        let workload_query = doc! { "_id":  workload_id.clone() };
+        let workload = match self
+            .workload_collection
+            .get_one_from(workload_query.clone())
+            .await?
+        {
+            Some(workload) => workload,
+            None => return Ok(()),
+        };
+        let policy = &workload.retry_policy;
+
+        let jobs_query = doc! { "workload_id": workload_id.clone() };
+        let jobs = self.job_collection.get_many_from(jobs_query).await?;
+        let mut total_failed_attempts = 0;
+        for job in jobs {
+            if host._id.as_ref() != Some(&job.host_id)
+                || !matches!(
+                    job.current_state,
+                    WorkloadState::Assigned | WorkloadState::Pending | WorkloadState::Installed
+                )
+            {
+                total_failed_attempts += job.failed_attempts;
+                continue;
+            }
+
+            let failed_attempts = job.failed_attempts + 1;
+            total_failed_attempts += failed_attempts;
+            let decision = retry::on_failed_attempt(policy, failed_attempts, err);
+            let retry_at = decision
+                .retry_in
+                .map(|retry_in| chrono::Utc::now().timestamp() + retry_in.as_secs() as i64);
+            log::info!(
+                "Workload install failed. MongodDB Job ID={:?}, Failed Attempts={}, Retry At={:?}",
+                job._id,
+                failed_attempts,
+                retry_at
+            );
+
+            let job_query = doc! { "_id": job._id.clone() };
+            let failed_job_doc = doc! {
+                "$set": {
+                    "current_state": bson::to_bson(&decision.state)?,
+                    "failed_attempts": failed_attempts,
+                    "retry_at": retry_at
+                }
+            };
+            self.job_collection
+                .update_one_within(job_query, UpdateModifications::Document(failed_job_doc))
+                .await?;
+            self.record_job_event(
+                &job.workload_id,
+                job._id,
+                Some(job.host_id),
+                JobEventSource::Orchestrator,
+                decision.state,
+            )
+            .await;
+        }
+
+        // Stop retrying a workload that keeps failing on its hosts
+        if retry::is_budget_exhausted(policy, total_failed_attempts) {
+            log::warn!(
+                "Workload exhausted its failure budget. MongodDB Workload ID={:?}, Failed Attempts={}",
+                workload_id,
+                total_failed_attempts
+            );
+            let failed_status = WorkloadStatus {
+                id: Some(workload_id.clone()),
+                host_device_id: None,
+                desired: WorkloadState::Running,
+                actual: WorkloadState::Failed(err.to_string()),
+            };
+            let failed_status_doc = doc! { "$set": { "status": bson::to_bson(&failed_status)? } };
+            self.workload_collection
+                .update_one_within(
+                    workload_query,
+                    UpdateModifications::Document(failed_status_doc),
+                )
+                .await?;
+
+            let jobs_query =
+                doc! { "workload_id": workload_id.clone(), "retry_at": { "$ne": Bson::Null } };
+            let failed_job_doc = doc! {
+                "$set": {
+                    "current_state": bson::to_bson(&WorkloadState::Failed(err.to_string()))?,
+                    "retry_at": Bson::Null
+                }
+            };
+            for job in self.job_collection.get_many_from(jobs_query).await? {
+                let job_query = doc! { "_id": job._id.clone() };
+                self.job_collection
+                    .update_one_within(
+                        job_query,
+                        UpdateModifications::Document(failed_job_doc.clone()),
+                    )
+                    .await?;
+                self.record_job_event(
+                    &job.workload_id,
+                    job._id,
+                    Some(job.host_id),
+                    JobEventSource::Orchestrator,
+                    WorkloadState::Failed(err.to_string()),
+                )
+                .await;
+            }
+        }
+        Ok(())
+    }
+
+    // Helper function to assign `count` more hosts to a workload, and start tracking its instances on them
+    // Returns the IDs of the hosts added (fewer than `count` when there are not enough eligible hosts)
+    // NB: The hosts the workload was preempted from are skipped, as they were handed over to workloads of a higher priority
+    async fn scale_out(&self, workload: &Workload, count: u16) -> Result<Vec<schemas::MongoDbId>> {
+        let workload_id = workload._id.clone().unwrap_or_default();
+        let preempted_jobs_query = doc! {
+            "workload_id": workload_id.clone(),
+            "current_state": bson::to_bson(&WorkloadState::Preempted)?
+        };
+        let preempted_host_ids: Vec<schemas::MongoDbId> = self
+            .job_collection
+            .get_many_from(preempted_jobs_query)
+            .await?
+            .into_iter()
+            .map(|job| job.host_id)
+            .collect();
+        let candidates: Vec<Host> = self
+            .get_eligible_hosts(workload)
+            .await?
+            .into_iter()
+            .filter(|h| {
+                h._id.as_ref().is_some_and(|id| {
+                    !workload.assigned_hosts.contains(id) && !preempted_host_ids.contains(id)
+                })
+            })
+            .collect();
+        let existing_hosts = self
+            .host_collection
+            .get_many_from(doc! { "_id": { "$in": workload.assigned_hosts.clone() } })
+            .await?;
+        let additional_hosts = Workload {
+            min_hosts: count,
+            ..workload.clone()
+        };
+
+        let mut added_host_ids = vec![];
+        for host in self
+            .scheduler
+            .place_alongside(&additional_hosts, candidates, &existing_hosts)
+        {
+            let host_id = host._id.unwrap_or_default();
+            self.workload_collection
+                .update_one_within(
+                    doc! { "_id": workload_id.clone() },
+                    UpdateModifications::Document(
+                        doc! { "$push": { "assigned_hosts": host_id.clone() } },
+                    ),
+                )
+                .await?;
+            self.host_collection
+                .update_one_within(
+                    doc! { "_id": host_id.clone() },
+                    UpdateModifications::Document(doc! {
+                        "$push": { "assigned_workloads": workload_id.clone() },
+                        "$inc": Self::remaining_capacity_inc(&workload.system_specs.capacity, -1)
+                    }),
+                )
+                .await?;
+            let job_id = self
+                .job_collection
+                .insert_one_into(Job {
+                    workload_id: workload_id.clone(),
+                    host_id: host_id.clone(),
+                    version: workload.version.clone(),
+                    ..Default::default()
+                })
+                .await?;
+            self.record_job_event(
+                &workload_id,
+                Some(job_id),
+                Some(host_id.clone()),
+                JobEventSource::Orchestrator,
+                WorkloadState::Assigned,
+            )
+            .await;
+            added_host_ids.push(host_id);
+        }
+        if added_host_ids.len() < count as usize {
+            log::warn!(
+                "Not enough eligible hosts to scale out workload. MongodDB Workload ID={:?}, Requested Hosts={}, Added Hosts={}",
+                workload_id,
+                count,
+                added_host_ids.len()
+            );
+        }
+        Ok(added_host_ids)
+    }
+
+    // Helper function to unassign the `count` most recently assigned hosts of a workload, and tear down its instances on them
+    async fn scale_in(&self, workload: &Workload, count: u16) -> Result<()> {
+        let workload_id = workload._id.clone().unwrap_or_default();
+        let removed_host_ids: Vec<schemas::MongoDbId> = workload
+            .assigned_hosts
+            .iter()
+            .rev()
+            .take(count as usize)
+            .cloned()
+            .collect();
+
+        self.workload_collection
+            .update_one_within(
+                doc! { "_id": workload_id.clone() },
+                UpdateModifications::Document(
+                    doc! { "$pull": { "assigned_hosts": { "$in": removed_host_ids.clone() } } },
+                ),
+            )
+            .await?;
+        for host_id in removed_host_ids {
+            self.host_collection
+                .update_one_within(
+                    doc! { "_id": host_id.clone() },
+                    UpdateModifications::Document(doc! {
+                        "$pull": { "assigned_workloads": workload_id.clone() },
+                        "$inc": Self::remaining_capacity_inc(&workload.system_specs.capacity, 1)
+                    }),
+                )
+                .await?;
+
+            let jobs_query = doc! {
+                "workload_id": workload_id.clone(),
+                "host_id": host_id.clone(),
+                "desired_state": { "$ne": bson::to_bson(&WorkloadState::Removed)? }
+            };
+            for job in self.job_collection.get_many_from(jobs_query).await? {
+                self.job_collection
+                    .update_one_within(
+                        doc! { "_id": job._id.clone() },
+                        UpdateModifications::Document(
+                            doc! { "$set": { "desired_state": bson::to_bson(&WorkloadState::Removed)? } },
+                        ),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Helper function to make room for a workload by evicting workloads of a lower priority from their hosts
+    // Returns the hosts freed up for the workload, with the capacity and workloads they are left with
+    // NB: The evicted workloads are left without the host until they are rescheduled by `reschedule_preempted_workloads` (their jobs are marked as `Preempted`)
+    async fn preempt_for(&self, workload: &Workload) -> Result<Vec<Host>> {
+        if workload.priority == 0 {
+            return Ok(vec![]);
+        }
+        let candidates = self.find_hosts(workload, false).await?;
+        let lower_priority_query = doc! {
+            "$or": [
+                { "priority": { "$lt": workload.priority as i32 } },
+                { "priority": { "$exists": false } }
+            ]
+        };
+        let lower_priority = self
+            .workload_collection
+            .get_many_from(lower_priority_query)
+            .await?;
+
+        let mut hosts = vec![];
+        for plan in self
+            .scheduler
+            .plan_preemption(workload, candidates, &lower_priority)
+        {
+            let host_id = plan.host._id.clone().unwrap_or_default();
+            for evicted_id in plan.evicted {
+                let evicted_capacity = lower_priority
+                    .iter()
+                    .find(|w| w._id.as_ref() == Some(&evicted_id))
+                    .map(|w| w.system_specs.capacity.clone())
+                    .unwrap_or_default();
+                log::info!(
+                    "Preempting workload to make room for a higher priority workload. MongodDB Workload ID={:?}, Host ID={:?}, Preempting Workload ID={:?}",
+                    evicted_id, host_id, workload._id
+                );
+                self.workload_collection
+                    .update_one_within(
+                        doc! { "_id": evicted_id.clone() },
+                        UpdateModifications::Document(
+                            doc! { "$pull": { "assigned_hosts": host_id.clone() } },
+                        ),
+                    )
+                    .await?;
+                self.host_collection
+                    .update_one_within(
+                        doc! { "_id": host_id.clone() },
+                        UpdateModifications::Document(doc! {
+                            "$pull": { "assigned_workloads": evicted_id.clone() },
+                            "$inc": Self::remaining_capacity_inc(&evicted_capacity, 1)
+                        }),
+                    )
+                    .await?;
+
+                let jobs_query = doc! {
+                    "workload_id": evicted_id.clone(),
+                    "host_id": host_id.clone(),
+                    "desired_state": { "$ne": bson::to_bson(&WorkloadState::Removed)? }
+                };
+                let preempted_job_doc = doc! {
+                    "$set": {
+                        "desired_state": bson::to_bson(&WorkloadState::Removed)?,
+                        "current_state": bson::to_bson(&WorkloadState::Preempted)?,
+                    }
+                };
+                for job in self.job_collection.get_many_from(jobs_query).await? {
+                    self.job_collection
+                        .update_one_within(
+                            doc! { "_id": job._id.clone() },
+                            UpdateModifications::Document(preempted_job_doc.clone()),
+                        )
+                        .await?;
+                    self.record_job_event(
+                        &evicted_id,
+                        job._id,
+                        Some(host_id.clone()),
+                        JobEventSource::Orchestrator,
+                        WorkloadState::Preempted,
+                    )
+                    .await;
+                }
+            }
+            hosts.push(plan.host);
+        }
+        Ok(hosts)
+    }
+
+    // Helper function to remember when the reconciler last sent the command of a job again, so that it is not sent on every pass
+    async fn set_job_reissued(&self, job: &Job, reissued_at: i64) -> Result<()> {
+        let job_query = doc! { "_id": job._id.clone() };
+        self.job_collection
+            .update_one_within(
+                job_query,
+                UpdateModifications::Document(doc! { "$set": { "last_reissued_at": reissued_at } }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    // Helper function to build the change of the remaining capacity of a host, when a workload is assigned to it (`sign` = -1)
+    // or unassigned from it (`sign` = 1)
+    // NB: The capacity is changed in the same update as the workloads assigned to the host, so that both always agree
+    fn remaining_capacity_inc(capacity: &schemas::Capacity, sign: i64) -> bson::Document {
+        doc! {
+            "remaining_capacity.cores": sign * capacity.cores,
+            "remaining_capacity.memory": sign * capacity.memory,
+            "remaining_capacity.disk": sign * capacity.disk,
+        }
+    }
+
+    // Helper function to flag (or unflag) a job as stuck in transition
+    async fn set_job_stuck(&self, job: &Job, stuck: bool) -> Result<()> {
+        let job_query = doc! { "_id": job._id.clone() };
+        self.job_collection
+            .update_one_within(
+                job_query,
+                UpdateModifications::Document(doc! { "$set": { "stuck": stuck } }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    // Helper function to reject a workload that would take its developer over their quota
+    // NB: Developers without a developer record are held to the default quota
+    async fn check_developer_quota(&self, workload: &Workload) -> Result<()> {
+        let developer_query = doc! { "_id": workload.assigned_developer.clone() };
+        let developer_quota = self
+            .developer_collection
+            .get_one_from(developer_query)
+            .await?
+            .map(|developer| developer.quota)
+            .unwrap_or_default();
+
+        let workloads_query = doc! { "assigned_developer": workload.assigned_developer.clone() };
+        let other_workloads: Vec<Workload> = self
+            .workload_collection
+            .get_many_from(workloads_query)
+            .await?
+            .into_iter()
+            .filter(|w| workload._id.is_none() || w._id != workload._id)
+            .collect();
+
+        quota::admit(&developer_quota, &other_workloads, workload)?;
+        Ok(())
+    }
+
+    // Helper function to turn a command that was already processed into a no-op
+    fn skip_duplicate_command(
+        &self,
+        command_id: &Option<String>,
+        workload_id: Option<schemas::MongoDbId>,
+    ) -> Option<types::ApiResult> {
+        let command_id = command_id.as_ref()?;
+        if !self.command_ledger.is_processed(command_id) {
+            return None;
+        }
+        log::info!(
+            "Skipping duplicate delivery of workload command. Command ID={}",
+            command_id
+        );
+        let status = WorkloadStatus {
+            id: workload_id,
+            host_device_id: self.host_device_id.clone(),
+            desired: WorkloadState::Unknown("..".to_string()),
+            actual: WorkloadState::Unknown(format!("Command {} was already processed", command_id)),
+        };
+        Some(types::ApiResult(status, None))
+    }
+
+    // Helper function to append a state transition to the event log of a workload
+    // NB: Failing to record an event is logged, but does not fail the state transition itself
+    async fn record_job_event(
+        &self,
+        workload_id: &schemas::MongoDbId,
+        job_id: Option<schemas::MongoDbId>,
+        host_id: Option<schemas::MongoDbId>,
+        source: JobEventSource,
+        state: WorkloadState,
+    ) {
+        let event = JobEvent {
+            _id: Some(ObjectId::new().to_hex()),
+            workload_id: workload_id.clone(),
+            job_id,
+            host_id,
+            source,
+            state,
+            created_at: chrono::Utc::now().timestamp_millis(),
+        };
+        if let Err(e) = self
+            .job_event_collection
+            .insert_one_into(event.clone())
+            .await
+        {
+            log::error!("Failed to record job event. Event={:?}, Err={:?}", event, e);
+        }
+    }
+
+    // Helper function to locate the host a workload status was reported by
+    async fn get_reporting_host(&self, status: &WorkloadStatus) -> Result<Option<Host>> {
+        let Some(device_id) = &status.host_device_id else {
+            return Ok(None);
+        };
+        let host_query = doc! { "device_id": device_id.clone() };
+        self.host_collection.get_one_from(host_query).await
+    }
+
+    // Helper function to check whether a workload is assigned to the host the api is run by (as the host agent)
+    async fn is_assigned_to_host(&self, workload_id: &schemas::MongoDbId) -> Result<bool> {
+        let Some(device_id) = &self.host_device_id else {
+            return Ok(false);
+        };
+        let host_query = doc! {
+            "device_id": device_id.clone(),
+            "assigned_workloads": workload_id.clone()
+        };
+        Ok(self
+            .host_collection
+            .get_one_from(host_query)
+            .await?
+            .is_some())
+    }
+
+    // Helper function to update the trust of the host a workload failed on
+    async fn record_failed_install(&self, host: &Host) -> Result<()> {
+        let mut trust = HostTrust {
+            failed_installs: host.trust.failed_installs + 1,
+            ..host.trust.clone()
+        };
+        trust.score = trust.compute_score();
+
+        if trust.score < MIN_HOST_TRUST_SCORE && host.trust.score >= MIN_HOST_TRUST_SCORE {
+            log::warn!(
+                "Host trust score dropped below the minimum. Host is no longer eligible for new workloads. MongodDB Host ID={:?}, Trust={:?}",
+                host._id,
+                trust
+            );
+        }
+
+        let host_query = doc! { "_id":  host._id.clone() };
+        let updated_trust_doc = doc! { "$set": { "trust": bson::to_bson(&trust)? } };
+        self.host_collection
+            .update_one_within(host_query, UpdateModifications::Document(updated_trust_doc))
+            .await?;
+        Ok(())
+    }
+
+    // Helper function to (un)cordon a host
+    async fn set_host_cordoned(&self, host_id: &schemas::MongoDbId, cordoned: bool) -> Result<()> {
+        let host_query = doc! { "_id":  host_id.clone() };
+        let updated_host_doc = doc! { "$set": { "cordoned": cordoned } };
+        self.host_collection
+            .update_one_within(host_query, UpdateModifications::Document(updated_host_doc))
+            .await?;
+        Ok(())
+    }
+
+    // Helper function to list the hosts of the workload's network that meet its requirements
+    async fn get_eligible_hosts(&self, workload: &Workload) -> Result<Vec<Host>> {
+        self.find_hosts(workload, true).await
+    }
+
+    // Helper function to list the hosts of the workload's network that pass the placement filters,
+    // ...optionally ignoring whether they have enough capacity left for the workload
+    async fn find_hosts(&self, workload: &Workload, require_capacity: bool) -> Result<Vec<Host>> {
+        // NB: Hosts registered before network pools were introduced have no `network` field, and belong to the mainnet
+        let network_filter = match workload.network {
+            Network::Mainnet => doc! { "$in": [bson::to_bson(&Network::Mainnet)?, Bson::Null] },
+            Network::Testnet => doc! { "$eq": bson::to_bson(&Network::Testnet)? },
+        };
+        // NB: Hosts with a trust score below the minimum are not eligible (hosts without any trust history are fully trusted)
+        // NB: Hosts with failing drives and cordoned hosts are not eligible
+        let mut host_filter = doc! {
+            "network": network_filter,
+            "$or": [
+                { "trust.score": { "$gte": MIN_HOST_TRUST_SCORE } },
+                { "trust": { "$exists": false } }
+            ],
+            "hardware.failing_drives": { "$not": { "$gt": 0 } },
+            "cordoned": { "$ne": true }
+        };
+        if require_capacity {
+            let capacity = &workload.system_specs.capacity;
+            host_filter.insert("remaining_capacity.cores", doc! { "$gte": capacity.cores });
+            host_filter.insert(
+                "remaining_capacity.memory",
+                doc! { "$gte": capacity.memory },
+            );
+            host_filter.insert("remaining_capacity.disk", doc! { "$gte": capacity.disk });
+        }
+        if workload.system_specs.gpus > 0 {
+            host_filter.insert(
+                "hardware.gpu_count",
+                doc! { "$gte": workload.system_specs.gpus },
+            );
+        }
+        // NB: Operators tune the host eligibility thresholds with the scheduling policies, without redeploying the service
+        let policies = self
+            .policy_collection
+            .get_many_from(doc! { "enabled": true })
+            .await?;
+        let policy_filters = SchedulingPolicy::host_filters(&policies);
+        if !policy_filters.is_empty() {
+            host_filter.insert("$and", policy_filters);
+        }
+        self.host_collection.get_many_from(host_filter).await
+    }
+
+    // Helper function to list the dependencies of a workload that do not (yet) report that they are running
+    async fn get_pending_dependencies(
+        &self,
+        workload: &Workload,
+    ) -> Result<Vec<schemas::MongoDbId>> {
+        if workload.dependencies.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let dependency_filter = doc! { "_id": { "$in": workload.dependencies.clone() } };
+        let running_dependencies: Vec<schemas::MongoDbId> = self
+            .workload_collection
+            .get_many_from(dependency_filter)
+            .await?
+            .into_iter()
+            .filter(|w| {
+                matches!(
+                    w.status,
+                    Some(WorkloadStatus {
+                        actual: WorkloadState::Running
+                            | WorkloadState::Healthy
+                            | WorkloadState::Maintenance,
+                        ..
+                    })
+                )
+            })
+            .filter_map(|w| w._id)
+            .collect();
+
+        Ok(workload
+            .dependencies
+            .iter()
+            .filter(|id| !running_dependencies.contains(id))
+            .cloned()
+            .collect())
+    }
+
     // Helper function to streamline the processing of incoming workload messages
     // NB: Currently used to process requests for MongoDB ops and the subsequent db change streams these db edits create (via the mongodb<>nats connector)
+    // Deserialize the request payload and run the handler on it.
+    // Handler errors are mapped into a status for the stream, as the response must always be a valid `ApiResult`.
     async fn process_request<T, Fut>(
         &self,
         msg: Arc<Message>,
         desired_state: WorkloadState,
         cb_fn: impl Fn(T) -> Fut + Send + Sync,
-        error_state: impl Fn(String) -> WorkloadState + Send + Sync,
     ) -> types::ApiResult
     where
         T: for<'de> Deserialize<'de> + Clone + Send + Sync + Debug + 'static,
-        Fut: Future<Output = Result<types::ApiResult, anyhow::Error>> + Send,
+        Fut: Future<Output = Result<types::ApiResult, types::ErrRequest>> + Send,
     {
-        // 1. Deserialize payload into the expected type
-        let payload: T = match serde_json::from_slice(&msg.payload) {
-            Ok(r) => r,
-            Err(e) => {
-                let err_msg = format!("Failed to deserialize payload for Workload Service Endpoint. Subject={} Error={:?}", msg.subject, e);
-                log::error!("{}", err_msg);
-                let status = WorkloadStatus {
-                    id: None,
-                    desired: desired_state,
-                    actual: error_state(err_msg),
-                };
-                return types::ApiResult(status, None);
-            }
+        // 1. Deserialize payload into the expected type, and call callback handler
+        let result = match serde_json::from_slice::<T>(&msg.payload) {
+            Ok(payload) => cb_fn(payload).await,
+            Err(e) => Err(e.into()),
         };
 
-        // 2. Call callback handler
-        match cb_fn(payload.clone()).await {
+        // 2. Map the error of the handler into the status of the response for the stream
+        match result {
             Ok(r) => r,
             Err(e) => {
-                let err_msg = format!("Failed to process Workload Service Endpoint. Subject={} Payload={:?}, Error={:?}", msg.subject, payload, e);
+                let err_msg = format!(
+                    "Failed to process Workload Service Endpoint. Subject={} Payload={} Error={}",
+                    msg.subject,
+                    String::from_utf8_lossy(&msg.payload),
+                    e
+                );
                 log::error!("{}", err_msg);
                 let status = WorkloadStatus {
-                    id: None,
+                    id: e.workload_id,
+                    host_device_id: None,
                     desired: desired_state,
-                    actual: error_state(err_msg),
+                    actual: WorkloadState::Error(err_msg),
                 };
-
-                // 3. return response for stream
                 types::ApiResult(status, None)
             }
         }
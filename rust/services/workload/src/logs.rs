@@ -0,0 +1,196 @@
+/*
+This module reads the logs of the workloads installed on a host, so that developers can debug a failing workload
+without access to the host.
+
+Every workload runs as its own systemd unit (see `unit_name`), whose logs are read from the journal,
+either as the last lines (tail) or as the lines within a time range.
+NB: journalctl matches unit names as globs, so only workload IDs that are MongoDB object IDs are accepted,
+otherwise a request for eg: the `*` workload would return the logs of every workload on the host.
+NB: At most `MAX_LOG_LINES` lines are returned per request, so that a response always fits in a single message.
+Longer ranges are read page by page, by requesting the range that starts right after the last line received.
+*/
+
+use anyhow::{anyhow, Context, Result};
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::process::Command;
+use util_libs::db::schemas::MongoDbId;
+
+use crate::types::LogsRequest;
+
+pub const MAX_LOG_LINES: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogLine {
+    pub timestamp: i64, // Unix timestamp (in millis)
+    pub message: String,
+}
+
+/// Systemd unit the workload runs as
+pub fn unit_name(workload_id: &MongoDbId) -> Result<String> {
+    // NB: The object ID is parsed from its hex form (ie: 24 hex digits), which cannot contain any glob character
+    ObjectId::parse_str(workload_id)
+        .map_err(|_| anyhow!("Invalid workload id: {workload_id:?}"))?;
+    Ok(format!("holo-workload-{workload_id}.service"))
+}
+
+/// Arguments of the `journalctl` invocation that reads the logs requested
+pub fn journalctl_args(request: &LogsRequest) -> Result<Vec<String>> {
+    let mut args = vec![
+        "--unit".to_string(),
+        unit_name(&request.workload_id)?,
+        "--output=json".to_string(),
+        "--no-pager".to_string(),
+    ];
+    let is_range = request.since.is_some() || request.until.is_some();
+    if let Some(since) = request.since {
+        args.push(format!("--since=@{}", millis_to_secs(since)));
+    }
+    if let Some(until) = request.until {
+        args.push(format!("--until=@{}", millis_to_secs(until)));
+    }
+    if is_range {
+        // NB: Ranges are read from their start, so that the next page starts right after the last line received
+        args.push(format!("--lines=+{}", MAX_LOG_LINES));
+    } else {
+        let tail = request.tail.unwrap_or(MAX_LOG_LINES).min(MAX_LOG_LINES);
+        args.push(format!("--lines={tail}"));
+    }
+    Ok(args)
+}
+
+/// Read the logs requested from the journal of the host
+pub async fn read_logs(request: &LogsRequest) -> Result<Vec<LogLine>> {
+    let output = Command::new("journalctl")
+        .args(journalctl_args(request)?)
+        .output()
+        .await
+        .context("running journalctl")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "journalctl failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let mut lines = parse_journal(&String::from_utf8_lossy(&output.stdout))?;
+    // NB: Journal timestamps are more precise than the requested range, so trim the lines that fall outside of it
+    lines.retain(|line| {
+        request.since.is_none_or(|since| line.timestamp >= since)
+            && request.until.is_none_or(|until| line.timestamp < until)
+    });
+    lines.truncate(MAX_LOG_LINES);
+    Ok(lines)
+}
+
+/// Parse the JSON export of the journal (one entry per line)
+pub fn parse_journal(output: &str) -> Result<Vec<LogLine>> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let entry: Value = serde_json::from_str(line).context("parsing journal entry")?;
+            let timestamp = entry["__REALTIME_TIMESTAMP"]
+                .as_str()
+                .and_then(|micros| micros.parse::<i64>().ok())
+                .ok_or(anyhow!("Journal entry without a timestamp: {line}"))?
+                / 1000;
+            // NB: Messages that are not valid UTF-8 are exported as an array of bytes
+            let message = match &entry["MESSAGE"] {
+                Value::String(message) => message.clone(),
+                Value::Array(bytes) => {
+                    let bytes: Vec<u8> = bytes
+                        .iter()
+                        .filter_map(|b| b.as_u64().map(|b| b as u8))
+                        .collect();
+                    String::from_utf8_lossy(&bytes).to_string()
+                }
+                _ => String::new(),
+            };
+            Ok(LogLine { timestamp, message })
+        })
+        .collect()
+}
+
+fn millis_to_secs(millis: i64) -> i64 {
+    millis.div_euclid(1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKLOAD_ID: &str = "65a1b2c3d4e5f60718293a4b";
+
+    #[test]
+    fn test_journalctl_args() -> Result<()> {
+        let tail = LogsRequest {
+            workload_id: WORKLOAD_ID.to_string(),
+            tail: Some(5000),
+            ..Default::default()
+        };
+        assert_eq!(
+            journalctl_args(&tail)?[..],
+            [
+                "--unit",
+                "holo-workload-65a1b2c3d4e5f60718293a4b.service",
+                "--output=json",
+                "--no-pager",
+                "--lines=1000"
+            ]
+        );
+
+        let range = LogsRequest {
+            workload_id: WORKLOAD_ID.to_string(),
+            since: Some(1_700_000_000_500),
+            ..Default::default()
+        };
+        assert_eq!(
+            journalctl_args(&range)?[4..],
+            ["--since=@1700000000", "--lines=+1000"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unit_name_rejects_globs() {
+        for workload_id in [
+            "*",
+            "65a1b2c3d4e5f60718293a4*",
+            "65a1b2c3d4e5f60718293a4[b]",
+            "65a1b2c3d4e5f60718293a4?",
+            "abc",
+            "",
+        ] {
+            assert!(unit_name(&workload_id.to_string()).is_err());
+            let request = LogsRequest {
+                workload_id: workload_id.to_string(),
+                ..Default::default()
+            };
+            assert!(journalctl_args(&request).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_journal() -> Result<()> {
+        let output = r#"
+{"__REALTIME_TIMESTAMP":"1700000000123456","MESSAGE":"conductor started"}
+{"__REALTIME_TIMESTAMP":"1700000001000000","MESSAGE":[104,105,255]}
+"#;
+        assert_eq!(
+            parse_journal(output)?,
+            vec![
+                LogLine {
+                    timestamp: 1_700_000_000_123,
+                    message: "conductor started".to_string()
+                },
+                LogLine {
+                    timestamp: 1_700_000_001_000,
+                    message: "hi\u{fffd}".to_string()
+                },
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+/*
+This module enforces the quota of a developer, so that a workload which would take the workloads of its developer
+over their quota is rejected before it is persisted or scaled up.
+
+The resources of a workload are counted once for every host it is assigned to (ie: `min_hosts` times its capacity).
+*/
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use util_libs::db::schemas::{DeveloperQuota, Workload};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub workloads: u32,
+    pub total_cores: i64,
+    pub total_disk: i64, // GiB
+}
+
+impl QuotaUsage {
+    /// Resources reserved by the given workloads
+    pub fn of(workloads: &[Workload]) -> Self {
+        workloads.iter().fold(Self::default(), |usage, workload| {
+            let hosts = workload.min_hosts as i64;
+            let capacity = &workload.system_specs.capacity;
+            Self {
+                workloads: usage.workloads + 1,
+                total_cores: usage.total_cores + capacity.cores * hosts,
+                total_disk: usage.total_disk + capacity.disk * hosts,
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrQuotaExceeded {
+    pub developer_id: String,
+    pub quota: DeveloperQuota,
+    pub requested: QuotaUsage, // Usage of the developer if the workload was admitted
+}
+impl fmt::Display for ErrQuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Quota exceeded for developer {}: {} workloads (max {}), {} cores (max {}), {} GiB of disk (max {})",
+            self.developer_id,
+            self.requested.workloads,
+            self.quota.max_workloads,
+            self.requested.total_cores,
+            self.quota.max_total_cores,
+            self.requested.total_disk,
+            self.quota.max_total_disk
+        )
+    }
+}
+impl Error for ErrQuotaExceeded {}
+
+/// Check whether `workload` can be admitted next to the other workloads of its developer
+/// NB: When the workload is an update of an existing workload, `existing` must not contain its previous version.
+pub fn admit(
+    quota: &DeveloperQuota,
+    existing: &[Workload],
+    workload: &Workload,
+) -> Result<(), ErrQuotaExceeded> {
+    let requested = QuotaUsage::of(&[existing, std::slice::from_ref(workload)].concat());
+    if requested.workloads > quota.max_workloads
+        || requested.total_cores > quota.max_total_cores
+        || requested.total_disk > quota.max_total_disk
+    {
+        return Err(ErrQuotaExceeded {
+            developer_id: workload.assigned_developer.clone(),
+            quota: quota.clone(),
+            requested,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workload(min_hosts: u16, cores: i64, disk: i64) -> Workload {
+        let mut workload = Workload {
+            min_hosts,
+            ..Default::default()
+        };
+        workload.system_specs.capacity.cores = cores;
+        workload.system_specs.capacity.disk = disk;
+        workload
+    }
+
+    #[test]
+    fn test_admit() {
+        let quota = DeveloperQuota {
+            max_workloads: 2,
+            max_total_cores: 8,
+            max_total_disk: 100,
+        };
+        let existing = vec![workload(2, 2, 10)];
+
+        assert!(admit(&quota, &existing, &workload(1, 4, 80)).is_ok());
+
+        // Scaling up counts the resources once per host
+        let err = admit(&quota, &existing, &workload(3, 2, 10)).unwrap_err();
+        assert_eq!(
+            err.requested,
+            QuotaUsage {
+                workloads: 2,
+                total_cores: 10,
+                total_disk: 50
+            }
+        );
+
+        let existing = vec![workload(1, 1, 1), workload(1, 1, 1)];
+        assert!(admit(&quota, &existing, &workload(1, 1, 1)).is_err());
+    }
+}
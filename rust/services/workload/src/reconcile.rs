@@ -0,0 +1,163 @@
+/*
+This module decides what the orchestrator does about a job whose actual state does not match its desired state,
+so that the workloads converge even when a command or a status report is lost along the way.
+
+A job that has been in transition for longer than `reissue_after` is sent its command again (at most once every
+`reissue_after`), and a job that has been in transition for longer than `stuck_after` is flagged as stuck and left
+for an operator to look into.
+NB: Jobs waiting for a retry, and jobs that failed for good, are handled by the retry policy instead.
+NB: The jobs are only reconciled by `WorkloadApi::reconcile_jobs`, which nothing calls in this tree yet.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use util_libs::db::schemas::{Job, WorkloadState};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReconcilerConfig {
+    pub reissue_after: Duration,
+    pub stuck_after: Duration,
+}
+
+impl Default for ReconcilerConfig {
+    fn default() -> Self {
+        Self {
+            reissue_after: Duration::from_secs(5 * 60),
+            stuck_after: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobAction {
+    Converged,
+    Wait,    // In transition, or handled by the retry policy
+    Reissue, // Send the command of the desired state again
+    Stuck,
+}
+
+/// Whether a job in the `current` state has reached the `desired` state
+pub fn is_converged(desired: &WorkloadState, current: &WorkloadState) -> bool {
+    match desired {
+        WorkloadState::Running => {
+            matches!(current, WorkloadState::Running | WorkloadState::Healthy)
+        }
+        WorkloadState::Removed => {
+            matches!(current, WorkloadState::Removed | WorkloadState::Uninstalled)
+        }
+        desired => desired == current,
+    }
+}
+
+/// Next action for the job, given when it last changed state (as a unix timestamp in millis)
+/// NB: A job without any recorded transition (nor reissue) is reissued right away, as there is no telling how long it has been waiting.
+pub fn next_action(
+    config: &ReconcilerConfig,
+    job: &Job,
+    last_transition_at: Option<i64>,
+    now: i64,
+) -> JobAction {
+    if is_converged(&job.desired_state, &job.current_state) {
+        return JobAction::Converged;
+    }
+    if job.retry_at.is_some() || matches!(job.current_state, WorkloadState::Failed(_)) {
+        return JobAction::Wait;
+    }
+
+    // NB: Reissuing a command is not a transition, so it only delays the next reissue (and not the stuck flag)
+    let in_transition_for = last_transition_at.map(|at| now.saturating_sub(at));
+    let Some(last_sent_at) = last_transition_at.max(job.last_reissued_at) else {
+        return JobAction::Reissue;
+    };
+    let waiting_for = now.saturating_sub(last_sent_at);
+    if in_transition_for.is_some_and(|d| d >= config.stuck_after.as_millis() as i64) {
+        JobAction::Stuck
+    } else if waiting_for >= config.reissue_after.as_millis() as i64 {
+        JobAction::Reissue
+    } else {
+        JobAction::Wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_action() {
+        let config = ReconcilerConfig::default();
+        let minute = 60 * 1000;
+        let job = Job {
+            current_state: WorkloadState::Pending,
+            ..Default::default()
+        };
+
+        assert_eq!(next_action(&config, &job, Some(0), minute), JobAction::Wait);
+        assert_eq!(
+            next_action(&config, &job, Some(0), 10 * minute),
+            JobAction::Reissue
+        );
+        assert_eq!(
+            next_action(&config, &job, Some(0), 60 * minute),
+            JobAction::Stuck
+        );
+        assert_eq!(next_action(&config, &job, None, 0), JobAction::Reissue);
+
+        // a reissued command is given time to land before being sent again, without delaying the stuck flag
+        let reissued = Job {
+            last_reissued_at: Some(8 * minute),
+            ..job.clone()
+        };
+        assert_eq!(
+            next_action(&config, &reissued, Some(0), 10 * minute),
+            JobAction::Wait
+        );
+        assert_eq!(
+            next_action(&config, &reissued, Some(0), 13 * minute),
+            JobAction::Reissue
+        );
+        assert_eq!(
+            next_action(&config, &reissued, None, 10 * minute),
+            JobAction::Wait
+        );
+        assert_eq!(
+            next_action(&config, &reissued, Some(0), 60 * minute),
+            JobAction::Stuck
+        );
+
+        // removed instances converge once their host acknowledges the uninstall
+        let removed = Job {
+            desired_state: WorkloadState::Removed,
+            current_state: WorkloadState::Preempted,
+            ..Default::default()
+        };
+        assert_eq!(next_action(&config, &removed, None, 0), JobAction::Reissue);
+        let uninstalled = Job {
+            current_state: WorkloadState::Uninstalled,
+            ..removed
+        };
+        assert_eq!(
+            next_action(&config, &uninstalled, None, 0),
+            JobAction::Converged
+        );
+
+        let healthy = Job {
+            current_state: WorkloadState::Healthy,
+            ..Default::default()
+        };
+        assert_eq!(
+            next_action(&config, &healthy, None, 0),
+            JobAction::Converged
+        );
+
+        let retrying = Job {
+            current_state: WorkloadState::Error("boom".to_string()),
+            retry_at: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            next_action(&config, &retrying, Some(0), 60 * minute),
+            JobAction::Wait
+        );
+    }
+}
@@ -0,0 +1,84 @@
+/*
+This module decides what happens to an instance of a workload (ie: a job) when its install fails on a host.
+
+The install is retried with an exponential backoff until the job has failed `max_attempts` times,
+at which point the job is marked as `Failed` and is no longer retried.
+NB: The workload as a whole is marked as `Failed` once its failure budget (ie: the failed attempts across all of its hosts)
+is exhausted, so that a broken workload does not keep on being retried on every host.
+NB: The retries that are due are only dispatched by `WorkloadApi::retry_due_jobs`, which nothing calls in this tree yet.
+*/
+
+use std::time::Duration;
+use util_libs::db::schemas::{RetryPolicy, WorkloadState};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryDecision {
+    pub state: WorkloadState,
+    pub retry_in: Option<Duration>, // None when the job is not retried
+}
+
+/// Backoff before the next install attempt, after `failed_attempts` failed attempts
+pub fn backoff(policy: &RetryPolicy, failed_attempts: u32) -> Duration {
+    let exponent = failed_attempts.saturating_sub(1).min(32);
+    let backoff_secs = policy
+        .initial_backoff_secs
+        .saturating_mul(2u64.saturating_pow(exponent))
+        .min(policy.max_backoff_secs);
+    Duration::from_secs(backoff_secs)
+}
+
+/// Next state of a job that just failed for the `failed_attempts`th time
+pub fn on_failed_attempt(policy: &RetryPolicy, failed_attempts: u32, err: &str) -> RetryDecision {
+    if failed_attempts >= policy.max_attempts {
+        return RetryDecision {
+            state: WorkloadState::Failed(err.to_string()),
+            retry_in: None,
+        };
+    }
+    RetryDecision {
+        state: WorkloadState::Error(err.to_string()),
+        retry_in: Some(backoff(policy, failed_attempts)),
+    }
+}
+
+/// Whether the failed attempts of a workload across all of its hosts exhaust its failure budget
+pub fn is_budget_exhausted(policy: &RetryPolicy, total_failed_attempts: u32) -> bool {
+    total_failed_attempts >= policy.failure_budget
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff_secs: 30,
+            max_backoff_secs: 100,
+            failure_budget: 10,
+        };
+        assert_eq!(backoff(&policy, 1), Duration::from_secs(30));
+        assert_eq!(backoff(&policy, 2), Duration::from_secs(60));
+        assert_eq!(backoff(&policy, 3), Duration::from_secs(100));
+        assert_eq!(backoff(&policy, 100), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn test_failed_attempts() {
+        let policy = RetryPolicy::default();
+        let decision = on_failed_attempt(&policy, 1, "boom");
+        assert_eq!(decision.state, WorkloadState::Error("boom".to_string()));
+        assert_eq!(
+            decision.retry_in,
+            Some(Duration::from_secs(policy.initial_backoff_secs))
+        );
+
+        let decision = on_failed_attempt(&policy, policy.max_attempts, "boom");
+        assert_eq!(decision.state, WorkloadState::Failed("boom".to_string()));
+        assert_eq!(decision.retry_in, None);
+
+        assert!(!is_budget_exhausted(&policy, policy.failure_budget - 1));
+        assert!(is_budget_exhausted(&policy, policy.failure_budget));
+    }
+}
@@ -0,0 +1,114 @@
+/*
+This module decides how many of the hosts of a workload receive an update next, according to the rollout strategy
+of the workload.
+
+A host is "in flight" from the moment it is sent the update until it reports the workload as running again
+(or as healthy, for a workload with a health check).
+- all at once: every host is updated immediately
+- rolling: at most `max_unavailable` hosts are in flight at the same time
+- canary: the first `canary_hosts` hosts are updated on their own, and the rollout only continues (as a rolling update)
+  once all of them report the workload as running
+NB: A host that fails to apply the update stays in flight, which halts the rollout until the failure is resolved.
+*/
+
+use util_libs::db::schemas::{RolloutStrategy, WorkloadState};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RolloutProgress {
+    pub updated: usize, // Hosts that were sent the update (including the ones in flight)
+    pub in_flight: usize, // Hosts that were sent the update but have not reported running since
+    pub remaining: usize, // Hosts that have not been sent the update yet
+}
+
+/// Whether an instance in the given state is up, ie: no longer in flight
+/// NB: An instance of a workload with a health check must pass it, as `Running` only means that it is installed
+pub fn is_up(has_health_check: bool, state: &WorkloadState) -> bool {
+    match state {
+        WorkloadState::Healthy | WorkloadState::Maintenance => true,
+        WorkloadState::Running => !has_health_check,
+        _ => false,
+    }
+}
+
+/// Number of hosts to send the update to next
+pub fn next_batch_size(strategy: &RolloutStrategy, progress: RolloutProgress) -> usize {
+    let batch_size = match strategy {
+        RolloutStrategy::AllAtOnce => progress.remaining,
+        RolloutStrategy::Rolling { max_unavailable } => {
+            rolling_batch_size(*max_unavailable, progress)
+        }
+        RolloutStrategy::Canary {
+            canary_hosts,
+            max_unavailable,
+        } => {
+            let canary_hosts = *canary_hosts as usize;
+            if progress.updated < canary_hosts {
+                // Send the update to the canaries that have not received it yet
+                canary_hosts - progress.updated
+            } else if progress.updated == canary_hosts && progress.in_flight > 0 {
+                // Wait for every canary to be healthy
+                0
+            } else {
+                rolling_batch_size(*max_unavailable, progress)
+            }
+        }
+    };
+    batch_size.min(progress.remaining)
+}
+
+fn rolling_batch_size(max_unavailable: u16, progress: RolloutProgress) -> usize {
+    // NB: Always allow one host to update, so that a `max_unavailable` of 0 does not stall the rollout
+    let max_unavailable = (max_unavailable as usize).max(1);
+    max_unavailable.saturating_sub(progress.in_flight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(updated: usize, in_flight: usize, remaining: usize) -> RolloutProgress {
+        RolloutProgress {
+            updated,
+            in_flight,
+            remaining,
+        }
+    }
+
+    #[test]
+    fn test_all_at_once_and_rolling() {
+        let all_at_once = RolloutStrategy::AllAtOnce;
+        assert_eq!(next_batch_size(&all_at_once, progress(0, 0, 5)), 5);
+
+        let rolling = RolloutStrategy::Rolling { max_unavailable: 2 };
+        assert_eq!(next_batch_size(&rolling, progress(0, 0, 5)), 2);
+        assert_eq!(next_batch_size(&rolling, progress(2, 1, 3)), 1);
+        assert_eq!(next_batch_size(&rolling, progress(4, 2, 1)), 0);
+        assert_eq!(next_batch_size(&rolling, progress(4, 0, 1)), 1);
+    }
+
+    #[test]
+    fn test_is_up() {
+        assert!(is_up(false, &WorkloadState::Running));
+        assert!(!is_up(true, &WorkloadState::Running));
+        assert!(is_up(true, &WorkloadState::Healthy));
+        assert!(!is_up(
+            true,
+            &WorkloadState::Unhealthy("timeout".to_string())
+        ));
+        assert!(!is_up(false, &WorkloadState::Pending));
+    }
+
+    #[test]
+    fn test_canary() {
+        let canary = RolloutStrategy::Canary {
+            canary_hosts: 1,
+            max_unavailable: 3,
+        };
+        // The canary goes first, alone
+        assert_eq!(next_batch_size(&canary, progress(0, 0, 5)), 1);
+        assert_eq!(next_batch_size(&canary, progress(1, 1, 4)), 0);
+        // ...and the others follow once it is healthy
+        assert_eq!(next_batch_size(&canary, progress(1, 0, 4)), 3);
+        assert_eq!(next_batch_size(&canary, progress(4, 2, 1)), 1);
+    }
+}
@@ -0,0 +1,400 @@
+/*
+This module ranks the hosts that are eligible for a workload (ie: the hosts that passed the placement filters)
+and picks the best `min_hosts` of them, honouring the placement constraints of the workload.
+
+Each host is given a score between 0.0 and 1.0 for every criterion, and the weighted average of those scores
+is scaled by the trust score of the host:
+- capacity headroom: how much capacity is left on the host once the workload is installed
+- uptime and network speed: relative to the best of the candidate hosts
+- load: how many workloads are already assigned to the host
+- jurisdiction: whether the host is located in one of the preferred jurisdictions
+
+The placement constraints of the workload are hard requirements:
+- only hosts located in one of the allowed jurisdictions are picked
+- hosts running one of the workloads to avoid are never picked
+- when spreading across hosters, no two replicas are placed on the hosts of the same hoster
+
+When no host has enough capacity left for a workload, the scheduler can plan the preemption of workloads
+of a lower priority: on every host, the lowest priority workloads are evicted until the workload fits,
+and the hosts that need the fewest evictions are picked.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use util_libs::db::schemas::{Capacity, Host, MongoDbId, PlacementConstraints, Workload};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScoringWeights {
+    pub capacity_headroom: f64,
+    pub uptime: f64,
+    pub network_speed: f64,
+    pub load: f64,
+    pub jurisdiction: f64,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            capacity_headroom: 0.3,
+            uptime: 0.25,
+            network_speed: 0.15,
+            load: 0.2,
+            jurisdiction: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Preemption {
+    pub host: Host, // With the capacity and workloads it is left with once the evicted workloads are removed
+    pub evicted: Vec<MongoDbId>, // MongoDB ID refs to the `workload._id`s evicted from the host
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Scheduler {
+    pub weights: ScoringWeights,
+    pub preferred_jurisdictions: Vec<String>, // Empty when every jurisdiction is equally preferred
+}
+
+impl Scheduler {
+    /// Pick the best `workload.min_hosts` hosts among `candidates`, best first.
+    /// NB: Fewer hosts are returned when there are not enough candidates.
+    pub fn place(&self, workload: &Workload, candidates: Vec<Host>) -> Vec<Host> {
+        self.place_alongside(workload, candidates, &[])
+    }
+
+    /// Same as `place`, for a workload that already runs on the `existing` hosts (eg: when replacing one of its hosts)
+    pub fn place_alongside(
+        &self,
+        workload: &Workload,
+        candidates: Vec<Host>,
+        existing: &[Host],
+    ) -> Vec<Host> {
+        let constraints = &workload.system_specs.constraints;
+        let candidates: Vec<Host> = candidates
+            .into_iter()
+            .filter(|host| satisfies_constraints(host, constraints))
+            .collect();
+
+        let mut scored: Vec<(f64, Host)> = self
+            .score_all(workload, &candidates)
+            .into_iter()
+            .zip(candidates)
+            .collect();
+        // NB: The sort is stable, so hosts with the same score keep the order they were fetched in
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        let host_count = (workload.min_hosts as usize).max(1);
+        let mut hosters: HashSet<String> =
+            existing.iter().map(|h| h.assigned_hoster.clone()).collect();
+        let mut placed = vec![];
+        for (_, host) in scored {
+            if placed.len() == host_count {
+                break;
+            }
+            // NB: `insert` returns false when a replica is already placed on a host of the same hoster
+            if constraints.spread_across_hosters && !hosters.insert(host.assigned_hoster.clone()) {
+                continue;
+            }
+            placed.push(host);
+        }
+        placed
+    }
+
+    /// Plan the evictions that make room for `workload` on up to `workload.min_hosts` of the `candidates`,
+    /// fewest evictions first.  `lower_priority` lists the workloads that may be evicted.
+    /// NB: Unlike `place`, the candidates are not expected to have enough capacity left for the workload.
+    pub fn plan_preemption(
+        &self,
+        workload: &Workload,
+        candidates: Vec<Host>,
+        lower_priority: &[Workload],
+    ) -> Vec<Preemption> {
+        let constraints = &workload.system_specs.constraints;
+        let required = &workload.system_specs.capacity;
+        let mut plans: Vec<Preemption> = candidates
+            .into_iter()
+            .filter(|host| satisfies_constraints(host, constraints))
+            .filter_map(|host| plan_host_preemption(host, required, workload, lower_priority))
+            .collect();
+        // NB: The sort is stable, so hosts needing as many evictions keep the order they were fetched in
+        plans.sort_by_key(|plan| plan.evicted.len());
+
+        let host_count = (workload.min_hosts as usize).max(1);
+        let mut hosters = HashSet::new();
+        plans
+            .into_iter()
+            .filter(|plan| {
+                !constraints.spread_across_hosters
+                    || hosters.insert(plan.host.assigned_hoster.clone())
+            })
+            .take(host_count)
+            .collect()
+    }
+
+    // Score every candidate for the workload.  Uptime and network speed are relative to the best candidate.
+    fn score_all(&self, workload: &Workload, candidates: &[Host]) -> Vec<f64> {
+        let max_uptime = candidates.iter().map(|h| h.avg_uptime).max().unwrap_or(0);
+        let max_network_speed = candidates
+            .iter()
+            .map(|h| h.avg_network_speed)
+            .max()
+            .unwrap_or(0);
+
+        candidates
+            .iter()
+            .map(|host| {
+                let w = &self.weights;
+                let weighted = w.capacity_headroom
+                    * capacity_headroom(&host.remaining_capacity, &workload.system_specs.capacity)
+                    + w.uptime * ratio(host.avg_uptime, max_uptime)
+                    + w.network_speed * ratio(host.avg_network_speed, max_network_speed)
+                    + w.load * (1.0 / (1.0 + host.assigned_workloads.len() as f64))
+                    + w.jurisdiction * self.jurisdiction_score(host);
+                let total_weight =
+                    w.capacity_headroom + w.uptime + w.network_speed + w.load + w.jurisdiction;
+                if total_weight <= 0.0 {
+                    return host.trust.score;
+                }
+                weighted / total_weight * host.trust.score
+            })
+            .collect()
+    }
+
+    fn jurisdiction_score(&self, host: &Host) -> f64 {
+        if self.preferred_jurisdictions.is_empty() {
+            return 1.0;
+        }
+        match &host.jurisdiction {
+            Some(jurisdiction) if self.preferred_jurisdictions.contains(jurisdiction) => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+// Evict the lowest priority workloads of the host until the required capacity fits, if it ever does
+fn plan_host_preemption(
+    mut host: Host,
+    required: &Capacity,
+    workload: &Workload,
+    lower_priority: &[Workload],
+) -> Option<Preemption> {
+    let mut evictable: Vec<&Workload> = lower_priority
+        .iter()
+        .filter(|w| w.priority < workload.priority)
+        .filter(|w| {
+            w._id
+                .as_ref()
+                .is_some_and(|id| host.assigned_workloads.contains(id))
+        })
+        .collect();
+    evictable.sort_by_key(|w| w.priority);
+
+    let fits = |capacity: &Capacity| {
+        capacity.cores >= required.cores
+            && capacity.memory >= required.memory
+            && capacity.disk >= required.disk
+    };
+    let mut evicted = vec![];
+    for victim in evictable {
+        if fits(&host.remaining_capacity) {
+            break;
+        }
+        let freed = &victim.system_specs.capacity;
+        host.remaining_capacity.cores += freed.cores;
+        host.remaining_capacity.memory += freed.memory;
+        host.remaining_capacity.disk += freed.disk;
+        evicted.extend(victim._id.clone());
+    }
+    if !fits(&host.remaining_capacity) {
+        return None;
+    }
+    host.assigned_workloads.retain(|id| !evicted.contains(id));
+    Some(Preemption { host, evicted })
+}
+
+fn satisfies_constraints(host: &Host, constraints: &PlacementConstraints) -> bool {
+    let allowed_jurisdiction = constraints.jurisdictions.is_empty()
+        || host
+            .jurisdiction
+            .as_ref()
+            .is_some_and(|j| constraints.jurisdictions.contains(j));
+    let avoids_workloads = !host
+        .assigned_workloads
+        .iter()
+        .any(|w| constraints.avoid_workloads.contains(w));
+    allowed_jurisdiction && avoids_workloads
+}
+
+// Share of the host capacity left once the workload is installed, averaged over cores, memory and disk
+fn capacity_headroom(remaining: &Capacity, required: &Capacity) -> f64 {
+    let headroom = |remaining: i64, required: i64| {
+        if remaining <= 0 {
+            return 0.0;
+        }
+        ((remaining - required) as f64 / remaining as f64).clamp(0.0, 1.0)
+    };
+    (headroom(remaining.cores, required.cores)
+        + headroom(remaining.memory, required.memory)
+        + headroom(remaining.disk, required.disk))
+        / 3.0
+}
+
+fn ratio(value: i64, max: i64) -> f64 {
+    if max <= 0 {
+        return 0.0;
+    }
+    (value as f64 / max as f64).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(id: &str, capacity: i64, uptime: i64, workloads: usize) -> Host {
+        Host {
+            _id: Some(id.to_string()),
+            remaining_capacity: Capacity {
+                memory: capacity,
+                disk: capacity,
+                cores: capacity,
+            },
+            avg_uptime: uptime,
+            avg_network_speed: 100,
+            assigned_workloads: vec![String::new(); workloads],
+            ..Default::default()
+        }
+    }
+
+    fn workload(min_hosts: u16) -> Workload {
+        let mut workload = Workload {
+            min_hosts,
+            ..Default::default()
+        };
+        workload.system_specs.capacity = Capacity {
+            memory: 10,
+            disk: 10,
+            cores: 10,
+        };
+        workload
+    }
+
+    fn placed_ids(hosts: Vec<Host>) -> Vec<String> {
+        hosts.into_iter().filter_map(|h| h._id).collect()
+    }
+
+    #[test]
+    fn test_place_best_hosts() {
+        let scheduler = Scheduler::default();
+        let candidates = vec![
+            host("busy", 100, 100, 10),
+            host("idle", 100, 100, 0),
+            host("small", 20, 100, 0),
+            host("flaky", 100, 10, 0),
+        ];
+
+        let placed = scheduler.place(&workload(2), candidates.clone());
+        assert_eq!(placed_ids(placed), vec!["idle", "small"]);
+
+        // At least one host is always picked
+        let placed = scheduler.place(&workload(0), candidates);
+        assert_eq!(placed_ids(placed), vec!["idle"]);
+    }
+
+    #[test]
+    fn test_trust_and_jurisdiction() {
+        let mut untrusted = host("untrusted", 100, 100, 0);
+        untrusted.trust.score = 0.5;
+        let mut remote = host("remote", 100, 100, 0);
+        remote.jurisdiction = Some("US".to_string());
+        let mut local = host("local", 100, 100, 0);
+        local.jurisdiction = Some("DE".to_string());
+
+        let scheduler = Scheduler {
+            weights: ScoringWeights::default(),
+            preferred_jurisdictions: vec!["DE".to_string()],
+        };
+        let placed = scheduler.place(&workload(3), vec![untrusted, remote, local]);
+        assert_eq!(placed_ids(placed), vec!["local", "remote", "untrusted"]);
+    }
+
+    #[test]
+    fn test_placement_constraints() {
+        let mut hosts: Vec<Host> = ["a1", "a2", "b1", "c1"]
+            .iter()
+            .map(|id| {
+                let mut host = host(id, 100, 100, 0);
+                host.assigned_hoster = id[..1].to_string();
+                host.jurisdiction = Some("DE".to_string());
+                host
+            })
+            .collect();
+        hosts[2].assigned_workloads = vec!["noisy".to_string()];
+        hosts[3].jurisdiction = Some("US".to_string());
+
+        let mut workload = workload(3);
+        workload.system_specs.constraints = PlacementConstraints {
+            jurisdictions: vec!["DE".to_string()],
+            spread_across_hosters: true,
+            avoid_workloads: vec!["noisy".to_string()],
+        };
+        let scheduler = Scheduler::default();
+        let placed = scheduler.place(&workload, hosts.clone());
+        assert_eq!(placed_ids(placed), vec!["a1"]);
+
+        // The hosters of the hosts the workload already runs on are avoided too
+        workload.system_specs.constraints.avoid_workloads = vec![];
+        let placed = scheduler.place_alongside(&workload, hosts.clone(), &hosts[..1]);
+        assert_eq!(placed_ids(placed), vec!["b1"]);
+    }
+
+    #[test]
+    fn test_plan_preemption() {
+        let running = |id: &str, priority: u8, capacity: i64| {
+            let mut running = workload(1);
+            running._id = Some(id.to_string());
+            running.priority = priority;
+            running.system_specs.capacity = Capacity {
+                memory: capacity,
+                disk: capacity,
+                cores: capacity,
+            };
+            running
+        };
+        let lower_priority = vec![
+            running("low", 0, 4),
+            running("mid", 1, 4),
+            running("critical", 5, 8),
+        ];
+
+        let mut two_evictions = host("two_evictions", 2, 100, 0);
+        two_evictions.assigned_workloads = vec!["low".to_string(), "mid".to_string()];
+        let mut one_eviction = host("one_eviction", 6, 100, 0);
+        one_eviction.assigned_workloads = vec!["mid".to_string()];
+        let mut never_fits = host("never_fits", 0, 100, 0);
+        never_fits.assigned_workloads = vec!["critical".to_string()];
+
+        let mut workload = workload(2);
+        workload.priority = 3;
+        let plans = Scheduler::default().plan_preemption(
+            &workload,
+            vec![two_evictions, one_eviction, never_fits],
+            &lower_priority,
+        );
+
+        let planned: Vec<(String, Vec<String>)> = plans
+            .into_iter()
+            .map(|plan| (plan.host._id.unwrap(), plan.evicted))
+            .collect();
+        assert_eq!(
+            planned,
+            vec![
+                ("one_eviction".to_string(), vec!["mid".to_string()]),
+                (
+                    "two_evictions".to_string(),
+                    vec!["low".to_string(), "mid".to_string()]
+                ),
+            ]
+        );
+    }
+}
@@ -1,6 +1,8 @@
+use crate::logs::LogLine;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use util_libs::{
-    db::schemas::WorkloadStatus,
+    db::schemas::{JobEvent, WorkloadStatus},
     js_stream_service::{CreateTag, EndpointTraits},
 };
 
@@ -16,3 +18,143 @@ impl CreateTag for ApiResult {
 }
 
 impl EndpointTraits for ApiResult {}
+
+// Error returned by a request handler, which is reported as a `WorkloadState::Error` status on the stream (with the workload, when known)
+// NB: Any error converts into a request error, so handlers can use `?` and attach the workload with `for_workload`
+#[derive(Debug)]
+pub struct ErrRequest {
+    pub workload_id: Option<WorkloadId>,
+    pub source: anyhow::Error,
+}
+impl fmt::Display for ErrRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.workload_id {
+            Some(workload_id) => write!(f, "{:?} (Workload ID={})", self.source, workload_id),
+            None => write!(f, "{:?}", self.source),
+        }
+    }
+}
+impl<E> From<E> for ErrRequest
+where
+    E: Into<anyhow::Error>,
+{
+    fn from(e: E) -> Self {
+        Self {
+            workload_id: None,
+            source: e.into(),
+        }
+    }
+}
+
+pub trait RequestContext<T> {
+    /// Attach the workload the request is about to the error, so that it is reported in the error status
+    fn for_workload(self, workload_id: &WorkloadId) -> Result<T, ErrRequest>;
+}
+impl<T, E> RequestContext<T> for Result<T, E>
+where
+    E: Into<ErrRequest>,
+{
+    fn for_workload(self, workload_id: &WorkloadId) -> Result<T, ErrRequest> {
+        self.map_err(|e| {
+            let mut err: ErrRequest = e.into();
+            err.workload_id.get_or_insert_with(|| workload_id.clone());
+            err
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceRequest {
+    pub workload_id: WorkloadId,
+    pub enabled: bool,
+}
+
+// Request rate of a workload, as published by the gateway
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrafficMetrics {
+    pub workload_id: WorkloadId,
+    pub requests_per_sec: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CordonRequest {
+    pub host_id: String,
+    pub cordoned: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LogsRequest {
+    pub workload_id: WorkloadId,
+    #[serde(default)]
+    pub tail: Option<usize>, // Last lines to return. Ignored when a time range is given
+    #[serde(default)]
+    pub since: Option<i64>, // Unix timestamp (in millis), inclusive
+    #[serde(default)]
+    pub until: Option<i64>, // Unix timestamp (in millis), exclusive
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogsResult {
+    pub workload_id: WorkloadId,
+    pub lines: Vec<LogLine>, // Oldest first
+}
+
+impl CreateTag for LogsResult {
+    fn get_tags(&self) -> Option<Vec<String>> {
+        None
+    }
+}
+
+impl EndpointTraits for LogsResult {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobEventsResult {
+    pub workload_id: WorkloadId,
+    pub events: Vec<JobEvent>, // Oldest first
+}
+
+impl CreateTag for JobEventsResult {
+    fn get_tags(&self) -> Option<Vec<String>> {
+        None
+    }
+}
+
+impl EndpointTraits for JobEventsResult {}
+
+// Upstream health transition of a host, as published by the gateway when it opens or closes the circuit of the host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostHealthTransition {
+    pub host_id: String,
+    pub healthy: bool,
+    #[serde(default)]
+    pub reason: Option<String>, // Last upstream error, when the circuit opens
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutesRequest {
+    #[serde(default)]
+    pub workload_ids: Vec<WorkloadId>, // Empty = every workload
+    #[serde(default)]
+    pub domain: Option<String>, // Only the workload the domain is mapped to (eg: from the Host header of a gateway request)
+}
+
+// Hosts able to serve the requests for a workload, in the order they should be tried
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WorkloadRoute {
+    pub workload_id: WorkloadId,
+    pub domains: Vec<String>,
+    pub hosts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingTable {
+    pub routes: Vec<WorkloadRoute>,
+}
+
+impl CreateTag for RoutingTable {
+    fn get_tags(&self) -> Option<Vec<String>> {
+        None
+    }
+}
+
+impl EndpointTraits for RoutingTable {}
@@ -0,0 +1,225 @@
+/*
+This module validates the workloads submitted by developers before they are persisted, so that a malformed
+workload is rejected up front with the list of its problems, instead of failing later on the hosts.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use url::Url;
+use util_libs::db::schemas::{HealthCheck, RolloutStrategy, Workload};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ValidationError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ErrInvalidWorkload(pub Vec<ValidationError>);
+impl fmt::Display for ErrInvalidWorkload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let errors: Vec<String> = self
+            .0
+            .iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect();
+        write!(f, "Invalid workload: {}", errors.join("; "))
+    }
+}
+impl Error for ErrInvalidWorkload {}
+
+/// Check every field of the workload, and return all of the problems found
+pub fn validate_workload(workload: &Workload) -> Result<(), ErrInvalidWorkload> {
+    let mut errors = vec![];
+    let mut check = |valid: bool, field: &str, message: String| {
+        if !valid {
+            errors.push(ValidationError {
+                field: field.to_string(),
+                message,
+            });
+        }
+    };
+
+    check(
+        semver::Version::parse(&workload.version).is_ok(),
+        "version",
+        format!("'{}' is not a valid semantic version", workload.version),
+    );
+    check(
+        !workload.nix_pkg.trim().is_empty(),
+        "nix_pkg",
+        "must not be empty".to_string(),
+    );
+    check(
+        workload.min_hosts > 0,
+        "min_hosts",
+        "must be at least 1".to_string(),
+    );
+
+    let specs = &workload.system_specs;
+    check(
+        specs.capacity.cores > 0,
+        "system_specs.capacity.cores",
+        format!("must be positive, got {}", specs.capacity.cores),
+    );
+    check(
+        specs.capacity.memory > 0,
+        "system_specs.capacity.memory",
+        format!("must be positive, got {}", specs.capacity.memory),
+    );
+    check(
+        specs.capacity.disk > 0,
+        "system_specs.capacity.disk",
+        format!("must be positive, got {}", specs.capacity.disk),
+    );
+    check(
+        specs.gpus >= 0,
+        "system_specs.gpus",
+        format!("must not be negative, got {}", specs.gpus),
+    );
+
+    if let Some(id) = &workload._id {
+        check(
+            !workload.dependencies.contains(id),
+            "dependencies",
+            "a workload cannot depend on itself".to_string(),
+        );
+    }
+
+    if let Some(HealthCheck::Http { url }) = &workload.health_check {
+        check(
+            Url::parse(url).is_ok_and(|url| url.scheme() == "http"),
+            "health_check.url",
+            format!("'{}' is not a valid http url", url),
+        );
+    }
+
+    if let Some(HealthCheck::ZomeCall {
+        dna_hash,
+        coordinator_identifier,
+        zome_name,
+        fn_name,
+        ..
+    }) = &workload.health_check
+    {
+        check(
+            [dna_hash, coordinator_identifier, zome_name, fn_name]
+                .iter()
+                .all(|segment| !segment.is_empty() && !segment.contains('/')),
+            "health_check",
+            "the dna hash, coordinator identifier, zome name and fn name of a zome call must be non-empty and must not contain '/'".to_string(),
+        );
+    }
+
+    if let RolloutStrategy::Canary { canary_hosts, .. } = &workload.rollout_strategy {
+        check(
+            *canary_hosts > 0,
+            "rollout_strategy.canary_hosts",
+            "must be at least 1".to_string(),
+        );
+    }
+
+    if let Some(autoscaling) = &workload.autoscaling {
+        check(
+            autoscaling.min_hosts > 0 && autoscaling.min_hosts <= autoscaling.max_hosts,
+            "autoscaling.min_hosts",
+            "must be at least 1, and must not exceed autoscaling.max_hosts".to_string(),
+        );
+        check(
+            autoscaling.target_requests_per_host > 0.0,
+            "autoscaling.target_requests_per_host",
+            "must be positive".to_string(),
+        );
+    }
+
+    for (i, domain) in workload.domains.iter().enumerate() {
+        check(
+            is_valid_domain(domain),
+            &format!("domains[{i}]"),
+            format!("'{}' is not a valid lowercase domain name", domain),
+        );
+        check(
+            !workload.domains[..i].contains(domain),
+            &format!("domains[{i}]"),
+            format!("'{}' is listed more than once", domain),
+        );
+    }
+
+    let retry_policy = &workload.retry_policy;
+    check(
+        retry_policy.max_attempts > 0,
+        "retry_policy.max_attempts",
+        "must be at least 1".to_string(),
+    );
+    check(
+        retry_policy.initial_backoff_secs <= retry_policy.max_backoff_secs,
+        "retry_policy.initial_backoff_secs",
+        "must not exceed retry_policy.max_backoff_secs".to_string(),
+    );
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ErrInvalidWorkload(errors))
+    }
+}
+
+// Lowercase domain name of at least two labels, each of up to 63 letters, digits or (inner) hyphens
+fn is_valid_domain(domain: &str) -> bool {
+    let labels: Vec<&str> = domain.split('.').collect();
+    domain.len() <= 253
+        && labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_workload() {
+        let valid_workload = Workload {
+            nix_pkg: "hello".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(validate_workload(&valid_workload), Ok(()));
+
+        let mut workload = Workload {
+            nix_pkg: "hello".to_string(),
+            version: "latest".to_string(),
+            min_hosts: 0,
+            health_check: Some(HealthCheck::Http {
+                url: "https://localhost/health".to_string(),
+            }),
+            domains: vec!["myapp.holohost.net".to_string(), "MyApp.net".to_string()],
+            ..Default::default()
+        };
+        workload.system_specs.capacity.cores = 0;
+
+        let fields: Vec<String> = validate_workload(&workload)
+            .unwrap_err()
+            .0
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                "version",
+                "min_hosts",
+                "system_specs.capacity.cores",
+                "health_check.url",
+                "domains[1]"
+            ]
+        );
+    }
+}
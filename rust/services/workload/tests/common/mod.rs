@@ -0,0 +1,44 @@
+// Helpers shared by the integration tests
+
+use anyhow::Result;
+use mongodb::{options::ClientOptions, Client};
+use tempfile::TempDir;
+
+// Ephemeral mongod instance, only reachable over a unix domain socket in its temp dir
+pub struct MongodRunner {
+    child: std::process::Child,
+    tempdir: TempDir, // NB: This is kept to prevent the premature removal of the tempdir
+}
+
+impl MongodRunner {
+    pub fn run() -> Result<Self> {
+        let tempdir = TempDir::new()?;
+        let socket_path = tempdir.path().canonicalize()?.join("mongod.sock");
+        std::fs::File::create_new(&socket_path)?;
+
+        let child = std::process::Command::new("mongod")
+            .arg("--unixSocketPrefix")
+            .arg(tempdir.path())
+            .arg("--dbpath")
+            .arg(tempdir.path())
+            .arg("--bind_ip")
+            .arg(&socket_path)
+            .args(["--port", "0"])
+            .spawn()?;
+        Ok(Self { child, tempdir })
+    }
+
+    pub fn client(&self) -> Result<Client> {
+        let server_address = mongodb::options::ServerAddress::Unix {
+            path: self.tempdir.path().canonicalize()?.join("mongod.sock"),
+        };
+        let client_options = ClientOptions::builder().hosts(vec![server_address]).build();
+        Ok(Client::with_options(client_options)?)
+    }
+}
+
+impl Drop for MongodRunner {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
@@ -0,0 +1,141 @@
+/*
+Integration tests of the orchestrator retries and reconciler, against the MongoDB write failures injected
+with the `fault_injection` feature.
+NB: These need `mongod` in the PATH, and are run with `cargo test -p workload --features fault_injection`.
+*/
+#![cfg(feature = "fault_injection")]
+
+mod common;
+
+use anyhow::Result;
+use bson::doc;
+use common::MongodRunner;
+use mongodb::Client;
+use std::time::Duration;
+use util_libs::db::mongodb::MongoDbAPI;
+use util_libs::db::schemas::{Job, JobEvent, WorkloadState};
+use util_libs::fault_injection::{FaultConfig, FaultInjector};
+use workload::{reconcile::ReconcilerConfig, WorkloadApi};
+
+// Helper function to set up a workload api whose job writes fail as injected by the returned faults
+async fn setup_api(client: &Client) -> Result<(WorkloadApi, FaultInjector)> {
+    let faults = FaultInjector::default();
+    let mut api = WorkloadApi::new(client).await?;
+    api.job_collection = api.job_collection.with_faults(faults.clone());
+    api.job_event_collection = api.job_event_collection.with_faults(faults.clone());
+    Ok((api, faults))
+}
+
+#[tokio::test]
+async fn test_retry_survives_failed_db_writes() -> Result<()> {
+    let mongod = MongodRunner::run()?;
+    let (api, faults) = setup_api(&mongod.client()?).await?;
+
+    // NB: The jobs are inserted without an id, as the orchestrator does
+    let job_id = api
+        .job_collection
+        .insert_one_into(Job {
+            workload_id: "workload_id".to_string(),
+            host_id: "host_id".to_string(),
+            desired_state: WorkloadState::Running,
+            current_state: WorkloadState::Error("install failed".to_string()),
+            failed_attempts: 1,
+            retry_at: Some(chrono::Utc::now().timestamp() - 1),
+            ..Default::default()
+        })
+        .await?;
+
+    // the retry is not lost when the job cannot be updated...
+    faults.set(FaultConfig {
+        db_write_failure_rate: 1.0,
+        ..Default::default()
+    });
+    assert!(api.retry_due_jobs().await.is_err());
+    let job = api
+        .job_collection
+        .get_one_from(doc! { "_id": job_id })
+        .await?
+        .unwrap();
+    assert!(job.retry_at.is_some());
+
+    // ...and is dispatched exactly once when the database recovers
+    faults.clear();
+    let results = api.retry_due_jobs().await?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].1, Some(vec!["host_id".to_string()]));
+    assert!(api.retry_due_jobs().await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reconciler_recovers_lost_commands() -> Result<()> {
+    let mongod = MongodRunner::run()?;
+    let (mut api, faults) = setup_api(&mongod.client()?).await?;
+    api.reconciler = ReconcilerConfig {
+        reissue_after: Duration::from_secs(60),
+        stuck_after: Duration::from_secs(120),
+    };
+
+    // a job whose install command was dropped, and one whose last transition was a long time ago
+    let lost_job_id = api
+        .job_collection
+        .insert_one_into(Job {
+            workload_id: "lost_workload_id".to_string(),
+            host_id: "host_id".to_string(),
+            desired_state: WorkloadState::Running,
+            current_state: WorkloadState::Pending,
+            ..Default::default()
+        })
+        .await?;
+    let stuck_job_id = api
+        .job_collection
+        .insert_one_into(Job {
+            workload_id: "stuck_workload_id".to_string(),
+            host_id: "host_id".to_string(),
+            desired_state: WorkloadState::Running,
+            current_state: WorkloadState::Pending,
+            ..Default::default()
+        })
+        .await?;
+    api.job_event_collection
+        .insert_one_into(JobEvent {
+            workload_id: "stuck_workload_id".to_string(),
+            job_id: Some(stuck_job_id.clone()),
+            state: WorkloadState::Pending,
+            created_at: chrono::Utc::now().timestamp_millis() - 3_600_000,
+            ..Default::default()
+        })
+        .await?;
+
+    // flagging the stuck job fails while the database is down...
+    faults.set(FaultConfig {
+        db_write_failure_rate: 1.0,
+        ..Default::default()
+    });
+    assert!(api.reconcile_jobs().await.is_err());
+
+    // ...and is caught up on the next pass, which also reissues the lost command
+    faults.clear();
+    let results = api.reconcile_jobs().await?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0.id, Some("lost_workload_id".to_string()));
+    assert_eq!(results[0].1, Some(vec!["host_id".to_string()]));
+
+    let stuck_job = api
+        .job_collection
+        .get_one_from(doc! { "_id": stuck_job_id })
+        .await?
+        .unwrap();
+    assert!(stuck_job.stuck);
+    let lost_job = api
+        .job_collection
+        .get_one_from(doc! { "_id": lost_job_id })
+        .await?
+        .unwrap();
+    assert!(!lost_job.stuck);
+    assert!(lost_job.last_reissued_at.is_some());
+
+    // the reissued command is given time to land before being sent again
+    assert!(api.reconcile_jobs().await?.is_empty());
+    Ok(())
+}
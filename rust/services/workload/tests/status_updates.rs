@@ -0,0 +1,87 @@
+/*
+Integration tests of the handling of the statuses reported by the host agents.
+NB: These need `mongod` in the PATH.
+*/
+
+mod common;
+
+use anyhow::Result;
+use async_nats::{Message, ServerInfo};
+use bson::doc;
+use common::MongodRunner;
+use std::sync::Arc;
+use util_libs::db::mongodb::MongoDbAPI;
+use util_libs::db::schemas::{Host, Job, Workload, WorkloadState};
+use workload::WorkloadApi;
+
+// Helper function to wrap a payload into a message, as received by an endpoint
+fn message(subject: &str, payload: Vec<u8>) -> Arc<Message> {
+    Arc::new(Message {
+        subject: subject.into(),
+        reply: None,
+        length: payload.len(),
+        payload: payload.into(),
+        headers: None,
+        status: None,
+        description: None,
+    })
+}
+
+#[tokio::test]
+async fn test_status_resolves_reporting_host() -> Result<()> {
+    let mongod = MongodRunner::run()?;
+    let client = mongod.client()?;
+    let orchestrator_api = WorkloadApi::new(&client).await?;
+
+    // a host registered under the ID of its leaf server, and the instance of a workload being removed from it
+    let server_info = ServerInfo {
+        server_id: "NDEVICEID".to_string(),
+        ..Default::default()
+    };
+    let host_id = orchestrator_api
+        .host_collection
+        .insert_one_into(Host {
+            device_id: server_info.server_id.clone(),
+            ..Default::default()
+        })
+        .await?;
+    let workload_id = orchestrator_api
+        .workload_collection
+        .insert_one_into(Workload::default())
+        .await?;
+    let job_id = orchestrator_api
+        .job_collection
+        .insert_one_into(Job {
+            workload_id: workload_id.clone(),
+            host_id,
+            desired_state: WorkloadState::Removed,
+            current_state: WorkloadState::Running,
+            ..Default::default()
+        })
+        .await?;
+
+    // the host agent acknowledges the uninstall under the ID it is connected to its leaf server with...
+    let mut agent_api = WorkloadApi::new(&client).await?;
+    agent_api.set_host_device_id(&server_info);
+    let ack = agent_api
+        .uninstall_workload(message(
+            "WORKLOAD.uninstall",
+            serde_json::to_vec(&workload_id)?,
+        ))
+        .await?;
+
+    // ...which the orchestrator resolves to the host, and records on its instance
+    orchestrator_api
+        .handle_status_update(message(
+            "WORKLOAD.read_status_update",
+            serde_json::to_vec(&ack.0)?,
+        ))
+        .await?;
+    let job = orchestrator_api
+        .job_collection
+        .get_one_from(doc! { "_id": job_id })
+        .await?
+        .unwrap();
+    assert_eq!(job.current_state, WorkloadState::Uninstalled);
+    Ok(())
+}
@@ -0,0 +1,284 @@
+/* --------
+This file contains the helpers used to export a sanitized copy of the production collections for staging
+environments and load tests.
+NB: All personal data (emails, ip addresses, jwts and pubkeys) is replaced with a salted hash, while every
+MongoDB ID is left untouched so that references between collections keep pointing at the same records.
+The same input always maps to the same output for a given salt, eg: a hoster pubkey stored in `user.roles`
+and in `host.assigned_hoster` are both replaced by the same value.
+-------- */
+
+use super::mongodb::{IntoIndexes, MongoCollection, MongoDbAPI};
+use super::schemas::{
+    self, Developer, Host, Hoster, Role, RoleInfo, User, Workload, DATABASE_NAME,
+};
+use anyhow::Result;
+use bson::doc;
+use mongodb::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Debug;
+
+pub const ANONYMIZED_EMAIL_DOMAIN: &str = "anonymized.invalid";
+
+pub trait Anonymize {
+    fn anonymize(self, salt: &str) -> Self;
+}
+
+// Helper function to deterministically replace a sensitive value with a salted hash
+pub fn pseudonymize(value: &str, salt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(value.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+impl Anonymize for User {
+    fn anonymize(self, salt: &str) -> Self {
+        let roles = self
+            .roles
+            .into_iter()
+            .map(|role_info| RoleInfo {
+                role: match role_info.role {
+                    Role::Developer(jwt) => Role::Developer(pseudonymize(&jwt, salt)),
+                    Role::Host(pubkey) => Role::Host(pseudonymize(&pubkey, salt)),
+                },
+                ..role_info
+            })
+            .collect();
+
+        Self {
+            email: format!(
+                "{}@{}",
+                pseudonymize(&self.email, salt),
+                ANONYMIZED_EMAIL_DOMAIN
+            ),
+            roles,
+            ..self
+        }
+    }
+}
+
+// No personal data is stored on the Developer record
+impl Anonymize for Developer {
+    fn anonymize(self, _salt: &str) -> Self {
+        self
+    }
+}
+
+// No personal data is stored on the Hoster record
+impl Anonymize for Hoster {
+    fn anonymize(self, _salt: &str) -> Self {
+        self
+    }
+}
+
+impl Anonymize for Host {
+    fn anonymize(self, salt: &str) -> Self {
+        Self {
+            ip_address: "0.0.0.0".to_string(),
+            assigned_hoster: pseudonymize(&self.assigned_hoster, salt),
+            ..self
+        }
+    }
+}
+
+// No personal data is stored on the Workload record
+impl Anonymize for Workload {
+    fn anonymize(self, _salt: &str) -> Self {
+        self
+    }
+}
+
+/// Copy every record of the `source` collection into the `target` collection, anonymizing each record on the way.
+/// Returns the number of exported records.
+pub async fn export_anonymized<T>(
+    source: &MongoCollection<T>,
+    target: &MongoCollection<T>,
+    salt: &str,
+) -> Result<usize>
+where
+    T: Serialize
+        + for<'de> Deserialize<'de>
+        + Unpin
+        + Send
+        + Sync
+        + Default
+        + IntoIndexes
+        + Debug
+        + Anonymize,
+{
+    let records = source.get_many_from(doc! {}).await?;
+    if records.is_empty() {
+        return Ok(0);
+    }
+
+    let anonymized_records: Vec<T> = records
+        .into_iter()
+        .map(|record| record.anonymize(salt))
+        .collect();
+
+    let ids = target.insert_many_into(anonymized_records).await?;
+    Ok(ids.len())
+}
+
+/// Export an anonymized copy of all holo-hosting collections into the `target_db_name` database
+pub async fn export_anonymized_database(
+    source_client: &Client,
+    target_client: &Client,
+    target_db_name: &str,
+    salt: &str,
+) -> Result<()> {
+    async fn export_collection<T>(
+        source_client: &Client,
+        target_client: &Client,
+        target_db_name: &str,
+        collection_name: &str,
+        salt: &str,
+    ) -> Result<()>
+    where
+        T: Serialize
+            + for<'de> Deserialize<'de>
+            + Unpin
+            + Send
+            + Sync
+            + Default
+            + IntoIndexes
+            + Debug
+            + Anonymize,
+    {
+        let source =
+            MongoCollection::<T>::new(source_client, DATABASE_NAME, collection_name).await?;
+        let mut target =
+            MongoCollection::<T>::new(target_client, target_db_name, collection_name).await?;
+        target.apply_indexing().await?;
+
+        let count = export_anonymized(&source, &target, salt).await?;
+        log::info!(
+            "Exported {} anonymized records from '{}' into '{}.{}'",
+            count,
+            collection_name,
+            target_db_name,
+            collection_name
+        );
+        Ok(())
+    }
+
+    export_collection::<User>(
+        source_client,
+        target_client,
+        target_db_name,
+        schemas::USER_COLLECTION_NAME,
+        salt,
+    )
+    .await?;
+    export_collection::<Developer>(
+        source_client,
+        target_client,
+        target_db_name,
+        schemas::DEVELOPER_COLLECTION_NAME,
+        salt,
+    )
+    .await?;
+    export_collection::<Hoster>(
+        source_client,
+        target_client,
+        target_db_name,
+        schemas::HOSTER_COLLECTION_NAME,
+        salt,
+    )
+    .await?;
+    export_collection::<Host>(
+        source_client,
+        target_client,
+        target_db_name,
+        schemas::HOST_COLLECTION_NAME,
+        salt,
+    )
+    .await?;
+    export_collection::<Workload>(
+        source_client,
+        target_client,
+        target_db_name,
+        schemas::WORKLOAD_COLLECTION_NAME,
+        salt,
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schemas::{Capacity, HostHardware, HostTrust, Network};
+
+    const SALT: &str = "test-salt";
+
+    #[test]
+    fn test_user_anonymization() {
+        let user = User {
+            _id: Some("user_id".to_string()),
+            email: "hoster@holo.host".to_string(),
+            jurisdiction: "Estonia".to_string(),
+            roles: vec![RoleInfo {
+                ref_id: "hoster_id".to_string(),
+                role: Role::Host("hoster_pubkey".to_string()),
+            }],
+        };
+
+        let anonymized = user.clone().anonymize(SALT);
+
+        assert_eq!(anonymized._id, user._id);
+        assert_eq!(anonymized.jurisdiction, user.jurisdiction);
+        assert!(!anonymized.email.contains("hoster@holo.host"));
+        assert!(anonymized.email.ends_with(ANONYMIZED_EMAIL_DOMAIN));
+        assert_eq!(anonymized.roles[0].ref_id, "hoster_id");
+        match &anonymized.roles[0].role {
+            Role::Host(pubkey) => assert_eq!(*pubkey, pseudonymize("hoster_pubkey", SALT)),
+            Role::Developer(_) => panic!("role type should be preserved"),
+        }
+
+        // Anonymization must be deterministic for a given salt
+        assert_eq!(anonymized.email, user.anonymize(SALT).email);
+    }
+
+    #[test]
+    fn test_host_anonymization_keeps_relations() {
+        let host = Host {
+            _id: Some("host_id".to_string()),
+            device_id: "Vf3IceiD".to_string(),
+            ip_address: "192.168.1.10".to_string(),
+            remaining_capacity: Capacity {
+                memory: 16,
+                disk: 200,
+                cores: 16,
+            },
+            avg_uptime: 95,
+            avg_network_speed: 500,
+            avg_latency: 10,
+            assigned_workloads: vec!["workload_id".to_string()],
+            assigned_hoster: "hoster_pubkey".to_string(),
+            network: Network::Mainnet,
+            trust: HostTrust::default(),
+            hardware: HostHardware::default(),
+            jurisdiction: None,
+            cordoned: false,
+            circuit_open: false,
+        };
+
+        let anonymized = host.clone().anonymize(SALT);
+
+        assert_eq!(anonymized._id, host._id);
+        assert_eq!(anonymized.device_id, host.device_id);
+        assert_eq!(anonymized.assigned_workloads, host.assigned_workloads);
+        assert_ne!(anonymized.ip_address, host.ip_address);
+        assert_eq!(
+            anonymized.assigned_hoster,
+            pseudonymize("hoster_pubkey", SALT)
+        );
+        assert_ne!(
+            anonymized.assigned_hoster,
+            host.anonymize("another-salt").assigned_hoster
+        );
+    }
+}
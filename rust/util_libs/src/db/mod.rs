@@ -1,2 +1,3 @@
+pub mod anonymize;
 pub mod mongodb;
 pub mod schemas;
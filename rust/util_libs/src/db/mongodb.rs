@@ -1,13 +1,18 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use bson::{self, doc, Document};
+use bson::{self, doc, oid::ObjectId, Document};
 use futures::stream::TryStreamExt;
-use mongodb::options::UpdateModifications;
+use mongodb::error::{ErrorKind, InsertManyError, PartialBulkWriteResult};
+use mongodb::options::{UpdateModifications, UpdateOneModel};
 use mongodb::results::{DeleteResult, UpdateResult};
 use mongodb::{options::IndexOptions, Client, Collection, IndexModel};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::Debug;
 
+// Number of documents sent to mongodb per batch by the bulk helpers
+pub const DEFAULT_BULK_CHUNK_SIZE: usize = 1000;
+
 #[derive(thiserror::Error, Debug, Clone)]
 pub enum ServiceError {
     #[error("Internal Error: {0}")]
@@ -25,15 +30,53 @@ where
     async fn get_many_from(&self, filter: Document) -> Result<Vec<T>>;
     async fn insert_one_into(&self, item: T) -> Result<String>;
     async fn insert_many_into(&self, items: Vec<T>) -> Result<Vec<String>>;
+    async fn insert_many_within(&self, items: Vec<T>, chunk_size: usize)
+        -> Result<BulkWriteReport>;
     async fn update_one_within(
         &self,
         query: Document,
         updated_doc: UpdateModifications,
     ) -> Result<UpdateResult>;
+    async fn update_bulk(
+        &self,
+        updates: Vec<(Document, UpdateModifications)>,
+        chunk_size: usize,
+    ) -> Result<BulkWriteReport>;
     async fn delete_one_from(&self, query: Document) -> Result<DeleteResult>;
     async fn delete_all_from(&self) -> Result<DeleteResult>;
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteOutcome {
+    Inserted(Option<String>), // Option<String> = inserted id
+    Updated { matched: u64, modified: u64 },
+    Failed(String), // String = error message
+}
+
+// Outcome of a bulk write, in the same order as the documents/updates that were submitted
+#[derive(Debug, Clone, Default)]
+pub struct BulkWriteReport {
+    pub outcomes: Vec<WriteOutcome>,
+}
+
+impl BulkWriteReport {
+    pub fn is_success(&self) -> bool {
+        self.failures().is_empty()
+    }
+
+    // Returns the index (within the submitted batch) and error message of every failed write
+    pub fn failures(&self) -> Vec<(usize, &str)> {
+        self.outcomes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, outcome)| match outcome {
+                WriteOutcome::Failed(err) => Some((index, err.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
 pub trait IntoIndexes {
     fn into_indices(self) -> Result<Vec<(Document, Option<IndexOptions>)>>;
 }
@@ -43,8 +86,11 @@ pub struct MongoCollection<T>
 where
     T: Serialize + for<'de> Deserialize<'de> + Unpin + Send + Sync + Default + IntoIndexes,
 {
+    client: Client, // NB: Bulk writes are run by the client, rather than by the collection
     collection: Collection<T>,
     indices: Vec<IndexModel>,
+    #[cfg(feature = "fault_injection")]
+    faults: crate::fault_injection::FaultInjector,
 }
 
 impl<T> MongoCollection<T>
@@ -62,11 +108,44 @@ where
         let indices = vec![];
 
         Ok(MongoCollection {
+            client: client.clone(),
             collection,
             indices,
+            #[cfg(feature = "fault_injection")]
+            faults: Default::default(),
         })
     }
 
+    /// Inject the faults into the writes made through this collection handle (and its clones)
+    #[cfg(feature = "fault_injection")]
+    pub fn with_faults(mut self, faults: crate::fault_injection::FaultInjector) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    // Helper function to serialize the items to insert, giving a (hex string) `_id` to the items without one
+    // NB: The schemas keep their ids as strings, so the ObjectId mongodb would generate could not be read back into them
+    fn to_documents_with_ids(items: Vec<T>) -> Result<(Vec<Document>, Vec<String>)> {
+        let mut documents = vec![];
+        let mut ids = vec![];
+        for item in items {
+            let mut document = bson::to_document(&item)?;
+            let id = match document.get("_id") {
+                Some(bson::Bson::String(id)) => id.to_owned(),
+                Some(bson::Bson::ObjectId(id)) => id.to_hex(),
+                Some(id) => return Err(anyhow!("Unsupported document id: {id}")),
+                None => {
+                    let id = ObjectId::new().to_hex();
+                    document.insert("_id", id.clone());
+                    id
+                }
+            };
+            documents.push(document);
+            ids.push(id);
+        }
+        Ok((documents, ids))
+    }
+
     pub async fn apply_indexing(&mut self) -> Result<&mut Self> {
         let schema_indices = T::default().into_indices()?;
         let mut indices = self.indices.to_owned();
@@ -114,27 +193,31 @@ where
     }
 
     async fn insert_one_into(&self, item: T) -> Result<String> {
-        let result = self
-            .collection
-            .insert_one(item)
+        #[cfg(feature = "fault_injection")]
+        self.faults.check_db_write()?;
+
+        let (documents, ids) = Self::to_documents_with_ids(vec![item])?;
+        self.collection
+            .clone_with_type::<Document>()
+            .insert_one(&documents[0])
             .await
             .map_err(ServiceError::Database)?;
 
-        Ok(result.inserted_id.to_string())
+        Ok(ids[0].to_owned())
     }
 
     async fn insert_many_into(&self, items: Vec<T>) -> Result<Vec<String>> {
-        let result = self
-            .collection
-            .insert_many(items)
+        #[cfg(feature = "fault_injection")]
+        self.faults.check_db_write()?;
+
+        // NB: The ids are returned in the order of the items
+        let (documents, ids) = Self::to_documents_with_ids(items)?;
+        self.collection
+            .clone_with_type::<Document>()
+            .insert_many(documents)
             .await
             .map_err(ServiceError::Database)?;
 
-        let ids = result
-            .inserted_ids
-            .values()
-            .map(|id| id.to_string())
-            .collect();
         Ok(ids)
     }
 
@@ -143,13 +226,149 @@ where
         query: Document,
         updated_doc: UpdateModifications,
     ) -> Result<UpdateResult> {
+        #[cfg(feature = "fault_injection")]
+        self.faults.check_db_write()?;
+
         self.collection
             .update_one(query, updated_doc)
             .await
             .map_err(|e| anyhow!(e))
     }
 
+    // NB: Documents are inserted unordered, so one failing document does not prevent the others (within or across chunks) from being inserted
+    async fn insert_many_within(
+        &self,
+        items: Vec<T>,
+        chunk_size: usize,
+    ) -> Result<BulkWriteReport> {
+        if chunk_size == 0 {
+            return Err(anyhow!("Bulk write chunk size must be greater than 0"));
+        }
+
+        #[cfg(feature = "fault_injection")]
+        self.faults.check_db_write()?;
+
+        let (documents, ids) = Self::to_documents_with_ids(items)?;
+        let collection = self.collection.clone_with_type::<Document>();
+        let mut report = BulkWriteReport::default();
+        for (chunk, chunk_ids) in documents.chunks(chunk_size).zip(ids.chunks(chunk_size)) {
+            let chunk_len = chunk.len();
+
+            match collection.insert_many(chunk).ordered(false).await {
+                Ok(_) => report.outcomes.extend(
+                    chunk_ids
+                        .iter()
+                        .map(|id| WriteOutcome::Inserted(Some(id.to_owned()))),
+                ),
+                Err(e) => match *e.kind {
+                    ErrorKind::InsertMany(InsertManyError {
+                        write_errors: Some(ref write_errors),
+                        ..
+                    }) => {
+                        let mut outcomes: Vec<WriteOutcome> = chunk_ids
+                            .iter()
+                            .map(|id| WriteOutcome::Inserted(Some(id.to_owned())))
+                            .collect();
+                        for write_error in write_errors.iter() {
+                            if let Some(outcome) = outcomes.get_mut(write_error.index) {
+                                *outcome = WriteOutcome::Failed(write_error.message.to_owned());
+                            }
+                        }
+                        report.outcomes.extend(outcomes);
+                    }
+                    // Any other error (eg: write concern or network error) is reported for the whole chunk
+                    _ => report
+                        .outcomes
+                        .extend(vec![WriteOutcome::Failed(e.to_string()); chunk_len]),
+                },
+            }
+        }
+
+        Ok(report)
+    }
+
+    // NB: Each chunk is sent as a single unordered bulk write, so one failing update does not prevent the others from being applied
+    // NB: Bulk writes are only supported by MongoDB 8.0+
+    async fn update_bulk(
+        &self,
+        updates: Vec<(Document, UpdateModifications)>,
+        chunk_size: usize,
+    ) -> Result<BulkWriteReport> {
+        if chunk_size == 0 {
+            return Err(anyhow!("Bulk write chunk size must be greater than 0"));
+        }
+
+        #[cfg(feature = "fault_injection")]
+        self.faults.check_db_write()?;
+
+        let mut report = BulkWriteReport::default();
+        let mut updates = updates.into_iter().peekable();
+        while updates.peek().is_some() {
+            let models: Vec<UpdateOneModel> = updates
+                .by_ref()
+                .take(chunk_size)
+                .map(|(query, updated_doc)| {
+                    UpdateOneModel::builder()
+                        .namespace(self.collection.namespace())
+                        .filter(query)
+                        .update(updated_doc)
+                        .build()
+                })
+                .collect();
+            let chunk_len = models.len();
+
+            let updated = |update_results: &HashMap<usize, UpdateResult>, index: usize| {
+                update_results.get(&index).map(|r| WriteOutcome::Updated {
+                    matched: r.matched_count,
+                    modified: r.modified_count,
+                })
+            };
+            match self
+                .client
+                .bulk_write(models)
+                .ordered(false)
+                .verbose_results()
+                .await
+            {
+                Ok(result) => report.outcomes.extend((0..chunk_len).map(|index| {
+                    updated(&result.update_results, index).unwrap_or(WriteOutcome::Failed(
+                        "No result reported for the update".to_string(),
+                    ))
+                })),
+                Err(e) => match *e.kind {
+                    ErrorKind::BulkWrite(ref bulk_write_error) => {
+                        let update_results = match &bulk_write_error.partial_result {
+                            Some(PartialBulkWriteResult::Verbose(result)) => {
+                                result.update_results.clone()
+                            }
+                            _ => HashMap::new(),
+                        };
+                        report.outcomes.extend((0..chunk_len).map(|index| {
+                            match bulk_write_error.write_errors.get(&index) {
+                                Some(write_error) => {
+                                    WriteOutcome::Failed(write_error.message.to_owned())
+                                }
+                                // NB: Updates without a result were not applied (eg: because of a write concern error)
+                                None => updated(&update_results, index)
+                                    .unwrap_or(WriteOutcome::Failed(e.to_string())),
+                            }
+                        }));
+                    }
+                    // Any other error (eg: network error) is reported for the whole chunk
+                    _ => report
+                        .outcomes
+                        .extend(vec![WriteOutcome::Failed(e.to_string()); chunk_len]),
+                },
+            }
+        }
+
+        Ok(report)
+    }
+
     async fn delete_one_from(&self, query: Document) -> Result<DeleteResult> {
+        #[cfg(feature = "fault_injection")]
+        self.faults.check_db_write()?;
+
         self.collection
             .delete_one(query)
             .await
@@ -157,6 +376,9 @@ where
     }
 
     async fn delete_all_from(&self) -> Result<DeleteResult> {
+        #[cfg(feature = "fault_injection")]
+        self.faults.check_db_write()?;
+
         self.collection
             .delete_many(doc! {})
             .await
@@ -288,6 +510,12 @@ mod tests {
                 avg_latency: 10,
                 assigned_workloads: vec!["workload_id".to_string()],
                 assigned_hoster: "hoster".to_string(),
+                network: schemas::Network::Mainnet,
+                trust: schemas::HostTrust::default(),
+                hardware: schemas::HostHardware::default(),
+                jurisdiction: None,
+                cordoned: false,
+                circuit_open: false,
             }
         }
 
@@ -336,4 +564,185 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_bulk_write_helpers() -> Result<()> {
+        let mongod = mongo_runner::MongodRunner::run().unwrap();
+        let client = mongod.client().unwrap();
+
+        let database_name = "holo-hosting-test";
+        let collection_name = "host";
+        let host_api =
+            MongoCollection::<schemas::Host>::new(&client, database_name, collection_name).await?;
+
+        let hosts: Vec<schemas::Host> = (0..5)
+            .map(|_| schemas::Host {
+                _id: Some(oid::ObjectId::new().to_string()),
+                ..Default::default()
+            })
+            .collect();
+
+        // insert in chunks, with one duplicate document that should fail without affecting the others
+        let mut items = hosts.clone();
+        items.insert(2, hosts[0].clone());
+        let report = host_api.insert_many_within(items, 2).await?;
+        assert_eq!(report.outcomes.len(), 6);
+        let failed_indices: Vec<usize> = report.failures().iter().map(|(i, _)| *i).collect();
+        assert_eq!(failed_indices, vec![2]);
+        let all_ids: Vec<String> = hosts.iter().map(|h| h._id.clone().unwrap()).collect();
+        let fetched_hosts = host_api
+            .get_many_from(doc! { "_id": { "$in": all_ids } })
+            .await?;
+        assert_eq!(fetched_hosts.len(), 5);
+
+        // update in chunks, reporting per-document match counts
+        let updates: Vec<(Document, UpdateModifications)> = hosts
+            .iter()
+            .map(|h| {
+                (
+                    doc! { "_id": h._id.clone() },
+                    UpdateModifications::Document(doc! { "$set": { "avg_latency": 42 } }),
+                )
+            })
+            .chain(std::iter::once((
+                doc! { "_id": "missing_host_id" },
+                UpdateModifications::Document(doc! { "$set": { "avg_latency": 42 } }),
+            )))
+            .collect();
+        let report = host_api.update_bulk(updates, 4).await?;
+        assert!(report.is_success());
+        assert_eq!(report.outcomes.len(), 6);
+        assert_eq!(
+            report.outcomes[0],
+            WriteOutcome::Updated {
+                matched: 1,
+                modified: 1
+            }
+        );
+        assert_eq!(
+            report.outcomes[5],
+            WriteOutcome::Updated {
+                matched: 0,
+                modified: 0
+            }
+        );
+
+        // reject empty chunks
+        assert!(host_api.update_bulk(vec![], 0).await.is_err());
+
+        host_api.delete_all_from().await?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_documents_with_ids() -> Result<()> {
+        let job_id = oid::ObjectId::new().to_hex();
+        let jobs = vec![
+            schemas::Job::default(),
+            schemas::Job {
+                _id: Some(job_id.clone()),
+                ..Default::default()
+            },
+        ];
+        let (documents, ids) = MongoCollection::<schemas::Job>::to_documents_with_ids(jobs)?;
+        assert!(oid::ObjectId::parse_str(&ids[0]).is_ok());
+        assert_eq!(ids[1], job_id);
+        for (document, id) in documents.into_iter().zip(ids) {
+            let job: schemas::Job = bson::from_document(document)?;
+            assert_eq!(job._id, Some(id));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_insert_without_id() -> Result<()> {
+        let mongod = mongo_runner::MongodRunner::run().unwrap();
+        let client = mongod.client().unwrap();
+
+        let job_api =
+            MongoCollection::<schemas::Job>::new(&client, "holo-hosting-test", "job").await?;
+        let job = schemas::Job {
+            workload_id: "workload_id".to_string(),
+            host_id: "host_id".to_string(),
+            ..Default::default()
+        };
+
+        // the generated ids are hex strings that can be read back into the schema...
+        let job_id = job_api.insert_one_into(job.clone()).await?;
+        assert!(oid::ObjectId::parse_str(&job_id).is_ok());
+        let fetched_job = job_api
+            .get_one_from(doc! { "_id": job_id.clone() })
+            .await?
+            .expect("Failed to fetch the inserted job");
+        assert_eq!(fetched_job._id, Some(job_id));
+
+        // ...and are returned in the order of the items, including by the bulk inserts
+        let job_ids = job_api
+            .insert_many_into(vec![job.clone(), job.clone()])
+            .await?;
+        let report = job_api
+            .insert_many_within(vec![job.clone(), job], 1)
+            .await?;
+        let bulk_job_ids: Vec<String> = report
+            .outcomes
+            .into_iter()
+            .filter_map(|outcome| match outcome {
+                WriteOutcome::Inserted(id) => id,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(bulk_job_ids.len(), 2);
+        for job_id in job_ids.into_iter().chain(bulk_job_ids) {
+            let fetched_job = job_api.get_one_from(doc! { "_id": job_id.clone() }).await?;
+            assert_eq!(fetched_job.and_then(|j| j._id), Some(job_id));
+        }
+        assert_eq!(job_api.get_many_from(doc! {}).await?.len(), 5);
+
+        job_api.delete_all_from().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_job_events_reference_jobs() -> Result<()> {
+        let mongod = mongo_runner::MongodRunner::run().unwrap();
+        let client = mongod.client().unwrap();
+
+        let job_api =
+            MongoCollection::<schemas::Job>::new(&client, "holo-hosting-test", "job").await?;
+        let job_event_api =
+            MongoCollection::<schemas::JobEvent>::new(&client, "holo-hosting-test", "job_event")
+                .await?;
+
+        let job_id = job_api
+            .insert_one_into(schemas::Job {
+                workload_id: "workload_id".to_string(),
+                host_id: "host_id".to_string(),
+                ..Default::default()
+            })
+            .await?;
+        job_event_api
+            .insert_one_into(schemas::JobEvent {
+                workload_id: "workload_id".to_string(),
+                job_id: Some(job_id.clone()),
+                state: schemas::WorkloadState::Assigned,
+                ..Default::default()
+            })
+            .await?;
+
+        // the events of a workload can be read back, and point to their job
+        let events = job_event_api
+            .get_many_from(doc! { "workload_id": "workload_id" })
+            .await?;
+        assert_eq!(events.len(), 1);
+        assert!(events[0]._id.is_some());
+        let job = job_api
+            .get_one_from(doc! { "_id": events[0].job_id.clone() })
+            .await?
+            .expect("Failed to fetch the job of the event");
+        assert_eq!(job._id, Some(job_id));
+
+        job_event_api.delete_all_from().await?;
+        job_api.delete_all_from().await?;
+        Ok(())
+    }
 }
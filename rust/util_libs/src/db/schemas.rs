@@ -11,6 +11,9 @@ pub const DEVELOPER_COLLECTION_NAME: &str = "developer";
 pub const HOSTER_COLLECTION_NAME: &str = "hoster";
 pub const HOST_COLLECTION_NAME: &str = "host";
 pub const WORKLOAD_COLLECTION_NAME: &str = "workload";
+pub const JOB_COLLECTION_NAME: &str = "job";
+pub const JOB_EVENT_COLLECTION_NAME: &str = "job_event";
+pub const SCHEDULING_POLICY_COLLECTION_NAME: &str = "scheduling_policies";
 
 // Provide type Alias for HosterPubKey
 pub use String as HosterPubKey;
@@ -83,6 +86,26 @@ pub struct Developer {
     pub _id: Option<MongoDbId>,
     pub user_id: String, // MongoDB ID ref to `user._id` (which stores the hoster's pubkey, jurisdiction and email)
     pub requested_workloads: Vec<String>, // MongoDB ID refs to `workload._id`
+    #[serde(default)]
+    pub quota: DeveloperQuota,
+}
+
+// Upper bound on the resources the workloads of a developer may reserve across all of their hosts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DeveloperQuota {
+    pub max_workloads: u32,
+    pub max_total_cores: i64,
+    pub max_total_disk: i64, // GiB
+}
+
+impl Default for DeveloperQuota {
+    fn default() -> Self {
+        Self {
+            max_workloads: 10,
+            max_total_cores: 64,
+            max_total_disk: 2048,
+        }
+    }
 }
 
 // No Additional Indexing for Developer
@@ -109,6 +132,15 @@ impl IntoIndexes for Hoster {
 }
 
 // ==================== Host Schema ====================
+// Segregated pool of hosts, so that workloads deployed to the testnet never run on production (mainnet) hosts
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Capacity {
     pub memory: i64, // GiB
@@ -116,6 +148,47 @@ pub struct Capacity {
     pub cores: i64,
 }
 
+// Behavioral history of a host, used to place fewer workloads on unreliable hosts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HostTrust {
+    pub score: f64, // 0.0 (untrusted) to 1.0 (fully trusted)
+    pub failed_installs: i64,
+    pub bogus_inventory_reports: i64,
+    pub auth_anomalies: i64,
+    pub gateway_errors: i64,
+}
+
+impl Default for HostTrust {
+    fn default() -> Self {
+        Self {
+            score: 1.0,
+            failed_installs: 0,
+            bogus_inventory_reports: 0,
+            auth_anomalies: 0,
+            gateway_errors: 0,
+        }
+    }
+}
+
+impl HostTrust {
+    // Each signal is weighted by how strongly it hints at a misbehaving (rather than unlucky) host
+    pub fn compute_score(&self) -> f64 {
+        let penalty = 0.05 * self.failed_installs as f64
+            + 0.25 * self.bogus_inventory_reports as f64
+            + 0.5 * self.auth_anomalies as f64
+            + 0.01 * self.gateway_errors as f64;
+        1.0 / (1.0 + penalty)
+    }
+}
+
+// Hardware attributes of a host that are relevant to scheduling, derived from its inventory
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct HostHardware {
+    pub gpu_count: i64,                       // *INDEXED*
+    pub failing_drives: i64,                  // *INDEXED*, Drives failing their SMART health check
+    pub max_temperature_celsius: Option<i64>, // Hottest temperature sensor reading
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Host {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -128,6 +201,18 @@ pub struct Host {
     pub avg_latency: i64,
     pub assigned_workloads: Vec<String>, // MongoDB ID refs to `workload._id`
     pub assigned_hoster: HosterPubKey,   // *INDEXED*, Hoster pubkey
+    #[serde(default)]
+    pub network: Network, // *INDEXED*
+    #[serde(default)]
+    pub trust: HostTrust,
+    #[serde(default)]
+    pub hardware: HostHardware,
+    #[serde(default)]
+    pub jurisdiction: Option<String>, // Country code of the location of the host
+    #[serde(default)]
+    pub cordoned: bool, // Cordoned hosts do not receive new workloads (eg: while being drained for maintenance)
+    #[serde(default)]
+    pub circuit_open: bool, // Set while the gateway skips the host after consecutive upstream errors/timeouts
 }
 
 impl IntoIndexes for Host {
@@ -143,35 +228,149 @@ impl IntoIndexes for Host {
         );
         indices.push((device_id_index_doc, device_id_index_opts));
 
+        //  Add Network Index
+        let network_index_doc = doc! { "network": 1 };
+        let network_index_opts = Some(
+            IndexOptions::builder()
+                .name(Some("network_index".to_string()))
+                .build(),
+        );
+        indices.push((network_index_doc, network_index_opts));
+
+        //  Add GPU Count Index
+        let gpu_count_index_doc = doc! { "hardware.gpu_count": 1 };
+        let gpu_count_index_opts = Some(
+            IndexOptions::builder()
+                .name(Some("gpu_count_index".to_string()))
+                .build(),
+        );
+        indices.push((gpu_count_index_doc, gpu_count_index_opts));
+
+        //  Add Failing Drives Index
+        let failing_drives_index_doc = doc! { "hardware.failing_drives": 1 };
+        let failing_drives_index_opts = Some(
+            IndexOptions::builder()
+                .name(Some("failing_drives_index".to_string()))
+                .build(),
+        );
+        indices.push((failing_drives_index_doc, failing_drives_index_opts));
+
         Ok(indices)
     }
 }
 
 // ==================== Workload Schema ====================
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum WorkloadState {
     Reported,
     Assigned, // String = host id
     Pending,
     Installed,
     Running,
+    Maintenance,       // Running on hosts, but withdrawn from the gateway routes
+    Healthy,           // Running and passing its health check
+    Unhealthy(String), // Running, but failing its health check. String = health check error
     Removed,
     Uninstalled,
     Error(String),   // String = error message
+    Failed(String),  // Gave up after exhausting the retry policy. String = last error message
+    Preempted, // Evicted from its host by a higher priority workload, and waiting to be rescheduled
     Unknown(String), // String = context message
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkloadStatus {
     pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_device_id: Option<String>, // Device ID of the host reporting the status. None = not reported by a host
     pub desired: WorkloadState,
     pub actual: WorkloadState,
 }
 
+// How the host agent checks that an installed workload is actually serving
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HealthCheck {
+    // eg: an endpoint of the workload exposed through hc-http-gw
+    Http {
+        url: String,
+    },
+    Tcp {
+        port: u16,
+    },
+    // Zome call (without a payload) made through the hc-http-gw instance of the host
+    ZomeCall {
+        gateway_port: u16, // Local port hc-http-gw listens on
+        dna_hash: String,
+        coordinator_identifier: String, // Identifier of the app the coordinator zome belongs to
+        zome_name: String,
+        fn_name: String,
+    },
+}
+
+// How failed installs of a workload are retried
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32, // Per host
+    pub initial_backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    pub failure_budget: u32, // Failed attempts allowed across all hosts before the workload is marked as failed
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_secs: 30,
+            max_backoff_secs: 900,
+            failure_budget: 10,
+        }
+    }
+}
+
+// Bounds within which the number of hosts of a workload follows its gateway traffic
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AutoscalingPolicy {
+    pub min_hosts: u16,
+    pub max_hosts: u16,
+    pub target_requests_per_host: f64, // Requests per second each host is expected to serve
+}
+
+// How an updated workload is rolled out to the hosts it is already installed on
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RolloutStrategy {
+    #[default]
+    AllAtOnce,
+    Rolling {
+        max_unavailable: u16, // Max number of hosts updating at the same time
+    },
+    Canary {
+        canary_hosts: u16, // Number of hosts updated first, which must all report healthy before the rollout continues
+        max_unavailable: u16,
+    },
+}
+
+// Where the hosts of a workload may (or may not) be located
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PlacementConstraints {
+    #[serde(default)]
+    pub jurisdictions: Vec<String>, // Jurisdictions the hosts must be located in. Empty when any jurisdiction is allowed
+    #[serde(default)]
+    pub spread_across_hosters: bool, // Place every replica on a host of a different hoster
+    #[serde(default)]
+    pub avoid_workloads: Vec<MongoDbId>, // MongoDB ID refs to the `workload._id`s the workload must not share a host with
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SystemSpecs {
-    pub capacity: Capacity, // network_speed: i64
-                            // uptime: i64
+    pub capacity: Capacity,
+    #[serde(default)]
+    pub gpus: i64, // Number of GPUs required
+    #[serde(default)]
+    pub constraints: PlacementConstraints,
+    // network_speed: i64
+    // uptime: i64
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -184,7 +383,24 @@ pub struct Workload {
     pub min_hosts: u16,
     pub system_specs: SystemSpecs,
     pub assigned_hosts: Vec<String>, // Host Device IDs (eg: assigned nats server id)
-                                     // pub status: WorkloadStatus,
+    #[serde(default)]
+    pub network: Network, // Pool of hosts the workload is deployed to
+    #[serde(default)]
+    pub dependencies: Vec<MongoDbId>, // MongoDB ID refs to the `workload._id`s that must be running before this workload is installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<WorkloadStatus>, // Latest status reported for the workload
+    #[serde(default)]
+    pub rollout_strategy: RolloutStrategy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<HealthCheck>,
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autoscaling: Option<AutoscalingPolicy>, // When set, `min_hosts` is adjusted to the gateway traffic of the workload
+    #[serde(default)]
+    pub domains: Vec<String>, // *INDEXED*, Custom domains the gateway routes to the workload (eg: `myapp.holohost.net`)
+    #[serde(default)]
+    pub priority: u8, // Workloads of a higher priority may preempt the workloads of a lower priority when hosts run out of capacity
 }
 
 impl Default for Workload {
@@ -211,8 +427,19 @@ impl Default for Workload {
                     disk: 400,
                     cores: 20,
                 },
+                gpus: 0,
+                constraints: PlacementConstraints::default(),
             },
             assigned_hosts: Vec::new(),
+            network: Network::default(),
+            dependencies: Vec::new(),
+            status: None,
+            rollout_strategy: RolloutStrategy::default(),
+            health_check: None,
+            retry_policy: RetryPolicy::default(),
+            autoscaling: None,
+            domains: Vec::new(),
+            priority: 0,
         }
     }
 }
@@ -230,6 +457,216 @@ impl IntoIndexes for Workload {
         );
         indices.push((developer_index_doc, developer_index_opts));
 
+        //  Add Domain Index
+        // NB: Only workloads with a domain are indexed, as workloads without any domain would otherwise collide on the unique index
+        let domain_index_doc = doc! { "domains": 1 };
+        let domain_index_opts = Some(
+            IndexOptions::builder()
+                .unique(true)
+                .partial_filter_expression(doc! { "domains.0": { "$exists": true } })
+                .name(Some("domains_index".to_string()))
+                .build(),
+        );
+        indices.push((domain_index_doc, domain_index_opts));
+
+        Ok(indices)
+    }
+}
+
+// ==================== Job Schema ====================
+// An instance of a workload on one of its assigned hosts
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Job {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<MongoDbId>,
+    pub workload_id: MongoDbId, // *INDEXED*, MongoDB ID ref to `workload._id`
+    pub host_id: MongoDbId,     // *INDEXED*, MongoDB ID ref to `host._id`
+    #[serde(default)]
+    pub version: SemVer, // Version of the workload installed (or being installed) on the host
+    pub desired_state: WorkloadState,
+    pub current_state: WorkloadState,
+    pub resource_usage: Option<Capacity>, // Latest resources used by the instance, as reported by its host
+    #[serde(default)]
+    pub failed_attempts: u32,
+    #[serde(default)]
+    pub retry_at: Option<i64>, // *INDEXED*, Unix timestamp (in secs) of the next install attempt
+    #[serde(default)]
+    pub stuck: bool, // Set by the reconciler when the job has been in transition for too long
+    #[serde(default)]
+    pub last_reissued_at: Option<i64>, // Unix timestamp (in millis) of the last time the reconciler sent the command of the job again
+}
+
+impl Default for Job {
+    fn default() -> Self {
+        Self {
+            _id: None,
+            workload_id: String::new(),
+            host_id: String::new(),
+            version: String::new(),
+            desired_state: WorkloadState::Running,
+            current_state: WorkloadState::Assigned,
+            resource_usage: None,
+            failed_attempts: 0,
+            retry_at: None,
+            stuck: false,
+            last_reissued_at: None,
+        }
+    }
+}
+
+impl IntoIndexes for Job {
+    fn into_indices(self) -> Result<Vec<(Document, Option<IndexOptions>)>> {
+        let mut indices = vec![];
+
+        //  Add Workload Index
+        let workload_index_doc = doc! { "workload_id": 1 };
+        let workload_index_opts = Some(
+            IndexOptions::builder()
+                .name(Some("workload_id_index".to_string()))
+                .build(),
+        );
+        indices.push((workload_index_doc, workload_index_opts));
+
+        //  Add Host Index
+        let host_index_doc = doc! { "host_id": 1 };
+        let host_index_opts = Some(
+            IndexOptions::builder()
+                .name(Some("host_id_index".to_string()))
+                .build(),
+        );
+        indices.push((host_index_doc, host_index_opts));
+
+        //  Add Retry Index
+        let retry_index_doc = doc! { "retry_at": 1 };
+        let retry_index_opts = Some(
+            IndexOptions::builder()
+                .name(Some("retry_at_index".to_string()))
+                .build(),
+        );
+        indices.push((retry_index_doc, retry_index_opts));
+
+        Ok(indices)
+    }
+}
+
+// ==================== Job Event Schema ====================
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobEventSource {
+    Host,
+    Orchestrator,
+}
+
+// An entry of the append-only log of the state transitions of the instances of a workload
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JobEvent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<MongoDbId>,
+    pub workload_id: MongoDbId, // *INDEXED*, MongoDB ID ref to `workload._id`
+    pub job_id: Option<MongoDbId>, // MongoDB ID ref to `job._id`. None when the event concerns every instance of the workload
+    pub host_id: Option<MongoDbId>, // MongoDB ID ref to `host._id`
+    pub source: JobEventSource,
+    pub state: WorkloadState, // State entered (includes the error message of error states)
+    pub created_at: i64,      // Unix timestamp (in millis)
+}
+
+impl Default for JobEvent {
+    fn default() -> Self {
+        Self {
+            _id: None,
+            workload_id: String::new(),
+            job_id: None,
+            host_id: None,
+            source: JobEventSource::Orchestrator,
+            state: WorkloadState::Reported,
+            created_at: 0,
+        }
+    }
+}
+
+impl IntoIndexes for JobEvent {
+    fn into_indices(self) -> Result<Vec<(Document, Option<IndexOptions>)>> {
+        let mut indices = vec![];
+
+        //  Add Workload Index
+        let workload_index_doc = doc! { "workload_id": 1, "created_at": 1 };
+        let workload_index_opts = Some(
+            IndexOptions::builder()
+                .name(Some("workload_id_created_at_index".to_string()))
+                .build(),
+        );
+        indices.push((workload_index_doc, workload_index_opts));
+
+        Ok(indices)
+    }
+}
+
+// ==================== Scheduling Policy Schema ====================
+// Host eligibility rules applied to every workload placement, on top of the requirements of the workload itself
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PolicyRule {
+    MinMemory { gib: i64 },
+    MinDisk { gib: i64 },
+    MinCores { cores: i64 },
+    MinNetworkSpeed { speed: i64 },
+    MinUptime { uptime: i64 },
+    Jurisdictions { allowed: Vec<String> },
+}
+
+impl PolicyRule {
+    // Host collection filter matching the hosts that satisfy the rule
+    pub fn to_host_filter(&self) -> Document {
+        match self {
+            PolicyRule::MinMemory { gib } => doc! { "remaining_capacity.memory": { "$gte": gib } },
+            PolicyRule::MinDisk { gib } => doc! { "remaining_capacity.disk": { "$gte": gib } },
+            PolicyRule::MinCores { cores } => {
+                doc! { "remaining_capacity.cores": { "$gte": cores } }
+            }
+            PolicyRule::MinNetworkSpeed { speed } => {
+                doc! { "avg_network_speed": { "$gte": speed } }
+            }
+            PolicyRule::MinUptime { uptime } => doc! { "avg_uptime": { "$gte": uptime } },
+            PolicyRule::Jurisdictions { allowed } => doc! { "jurisdiction": { "$in": allowed } },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SchedulingPolicy {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<MongoDbId>,
+    pub name: String, // *INDEXED*
+    pub enabled: bool,
+    pub rules: Vec<PolicyRule>,
+}
+
+impl SchedulingPolicy {
+    // Host collection filters for every rule of the given policies, to be combined with `$and`
+    // NB: Disabled policies are ignored
+    pub fn host_filters(policies: &[SchedulingPolicy]) -> Vec<Document> {
+        policies
+            .iter()
+            .filter(|policy| policy.enabled)
+            .flat_map(|policy| policy.rules.iter().map(PolicyRule::to_host_filter))
+            .collect()
+    }
+}
+
+impl IntoIndexes for SchedulingPolicy {
+    fn into_indices(self) -> Result<Vec<(Document, Option<IndexOptions>)>> {
+        let mut indices = vec![];
+
+        //  Add Name Index
+        let name_index_doc = doc! { "name": 1 };
+        let name_index_opts = Some(
+            IndexOptions::builder()
+                .name(Some("name_index".to_string()))
+                .unique(true)
+                .build(),
+        );
+        indices.push((name_index_doc, name_index_opts));
+
         Ok(indices)
     }
 }
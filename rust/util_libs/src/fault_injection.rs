@@ -0,0 +1,106 @@
+/* --------
+This file contains the fault-injection hooks used by the integration tests to verify how the services
+(eg: the reconciler, retries and dedup logic) behave under realistic failure modes.
+NB: This module (and every hook calling into it) is only compiled with the `fault_injection` feature,
+so it never ships in production builds.
+
+Faults are scoped to the clients and collections a `FaultInjector` is attached to
+(with `JsClient::with_faults`, `JsStreamService::with_faults` or `MongoCollection::with_faults`),
+so that tests running in parallel do not inject faults into each other.
+
+Supported faults:
+- dropping a share of the messages published with `JsClient::publish`, and of the response messages
+  published by the stream service consumers (the publish still reports success)
+- delaying the completion of every JetStream endpoint handler
+- failing a share of the MongoDB writes
+-------- */
+
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultConfig {
+    pub publish_drop_rate: f64, // Share of published messages to drop, between 0.0 and 1.0
+    pub handler_delay: Option<Duration>,
+    pub db_write_failure_rate: f64, // Share of MongoDB writes to fail, between 0.0 and 1.0
+}
+
+// Faults injected into the handles it is attached to. Injects no fault by default.
+// NB: Clones share the same config, so a test can change the faults of a running service through its own clone.
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {
+    config: Arc<RwLock<FaultConfig>>,
+}
+
+impl FaultInjector {
+    pub fn new(config: FaultConfig) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(config)),
+        }
+    }
+
+    /// Replace the faults currently injected into the attached handles
+    pub fn set(&self, config: FaultConfig) {
+        let mut faults = self.config.write().unwrap_or_else(|e| e.into_inner());
+        *faults = config;
+    }
+
+    /// Stop injecting faults
+    pub fn clear(&self) {
+        self.set(FaultConfig::default());
+    }
+
+    pub fn config(&self) -> FaultConfig {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub(crate) fn should_drop_publish(&self) -> bool {
+        should_inject(self.config().publish_drop_rate)
+    }
+
+    pub(crate) async fn delay_handler(&self) {
+        if let Some(delay) = self.config().handler_delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    pub(crate) fn check_db_write(&self) -> Result<()> {
+        if should_inject(self.config().db_write_failure_rate) {
+            return Err(anyhow!("Injected fault: MongoDB write failed"));
+        }
+        Ok(())
+    }
+}
+
+// Helper function to roll the dice for a fault with the given rate
+fn should_inject(rate: f64) -> bool {
+    rate > 0.0 && rand::thread_rng().gen_bool(rate.min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fault_rates() {
+        let faults = FaultInjector::new(FaultConfig {
+            publish_drop_rate: 1.0,
+            handler_delay: Some(Duration::from_millis(10)),
+            db_write_failure_rate: 0.0,
+        });
+        assert!(faults.should_drop_publish());
+        assert!(faults.check_db_write().is_ok());
+
+        // clones share their faults, other injectors are not affected
+        let other_faults = FaultInjector::default();
+        faults.clone().clear();
+        assert_eq!(faults.config(), FaultConfig::default());
+        assert!(!faults.should_drop_publish());
+        assert_eq!(other_faults.config(), FaultConfig::default());
+    }
+}
@@ -4,6 +4,7 @@ use anyhow::{anyhow, Result};
 use std::any::Any;
 // use async_nats::jetstream::message::Message;
 use async_nats::jetstream::consumer::{self, AckPolicy, PullConsumer};
+use async_nats::jetstream::context::Publish;
 use async_nats::jetstream::stream::{self, Info, Stream};
 use async_nats::jetstream::Context;
 use async_trait::async_trait;
@@ -115,6 +116,8 @@ pub struct JsStreamService {
     js_context: Arc<RwLock<Context>>,
     stream: Arc<RwLock<Stream<Info>>>,
     local_consumers: Arc<RwLock<HashMap<String, Arc<dyn ConsumerExtTrait>>>>,
+    #[cfg(feature = "fault_injection")]
+    faults: crate::fault_injection::FaultInjector,
 }
 
 impl JsStreamService {
@@ -150,9 +153,18 @@ impl JsStreamService {
             js_context: Arc::new(RwLock::new(context)),
             stream: Arc::new(RwLock::new(stream)),
             local_consumers: Arc::new(RwLock::new(HashMap::new())),
+            #[cfg(feature = "fault_injection")]
+            faults: Default::default(),
         })
     }
 
+    /// Inject the faults into the consumer handlers spawned from now on
+    #[cfg(feature = "fault_injection")]
+    pub fn with_faults(mut self, faults: crate::fault_injection::FaultInjector) -> Self {
+        self.faults = faults;
+        self
+    }
+
     pub fn get_service_info(&self) -> JsStreamServiceInfo {
         JsStreamServiceInfo {
             name: self.name.as_ref(),
@@ -180,6 +192,16 @@ impl JsStreamService {
         }
     }
 
+    pub async fn get_consumers_info(&self) -> Result<Vec<consumer::Info>> {
+        let mut infos = vec![];
+        for consumer_ext in self.local_consumers.read().await.values() {
+            let mut consumer = consumer_ext.get_consumer();
+            let info = consumer.info().await?;
+            infos.push(info.to_owned());
+        }
+        Ok(infos)
+    }
+
     pub async fn get_consumer<T>(&self, consumer_name: &str) -> Result<ConsumerExt<T>>
     where
         T: EndpointTraits,
@@ -288,6 +310,8 @@ impl JsStreamService {
             };
 
             let service_context = self.js_context.clone();
+            #[cfg(feature = "fault_injection")]
+            let faults = self.faults.clone();
 
             tokio::spawn(async move {
                 Self::process_messages(
@@ -296,6 +320,8 @@ impl JsStreamService {
                     messages,
                     endpoint_handler,
                     maybe_response_generator,
+                    #[cfg(feature = "fault_injection")]
+                    faults,
                 )
                 .await;
             });
@@ -318,6 +344,7 @@ impl JsStreamService {
         mut messages: consumer::pull::Stream,
         endpoint_handler: EndpointType<T>,
         maybe_response_generator: Option<ResponseSubjectsGenerator>,
+        #[cfg(feature = "fault_injection")] faults: crate::fault_injection::FaultInjector,
     ) where
         T: EndpointTraits,
     {
@@ -331,6 +358,9 @@ impl JsStreamService {
                 log_info.service_name
             );
 
+            #[cfg(feature = "fault_injection")]
+            faults.delay_handler().await;
+
             let result = match endpoint_handler {
                 EndpointType::Sync(ref handler) => handler(&js_msg.message),
                 EndpointType::Async(ref handler) => handler(Arc::new(js_msg.clone().message)).await,
@@ -382,12 +412,35 @@ impl JsStreamService {
             if let Some(response_subject_fn) = maybe_response_generator.as_ref() {
                 let response_subjects = response_subject_fn(maybe_subject_tags);
                 for response_subject in response_subjects.iter() {
+                    #[cfg(feature = "fault_injection")]
+                    if faults.should_drop_publish() {
+                        log::warn!(
+                            "{}Injected fault: dropped response message: subj='{}.{}', endpoint={}, service={}",
+                            log_info.prefix,
+                            log_info.service_subject,
+                            response_subject,
+                            log_info.endpoint_name,
+                            log_info.service_name
+                        );
+                        continue;
+                    }
+
+                    // NB: The message id is derived from the consumed message, so that the response messages
+                    // published again upon a redelivery of the consumed message are dropped as duplicates by JetStream,
+                    // and can be recognized as such by their consumers.
+                    let mut publish = Publish::build().payload(response_bytes.clone());
+                    if let Ok(info) = js_msg.info() {
+                        publish = publish.message_id(format!(
+                            "{}.{}.{}",
+                            info.stream, info.stream_sequence, response_subject
+                        ));
+                    }
                     if let Err(err) = service_context
                         .read()
                         .await
-                        .publish(
+                        .send_publish(
                             format!("{}.{}", log_info.service_subject, response_subject),
-                            response_bytes.clone(),
+                            publish,
                         )
                         .await
                     {
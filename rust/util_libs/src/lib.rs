@@ -1,5 +1,9 @@
 pub mod db;
+#[cfg(feature = "fault_injection")]
+pub mod fault_injection;
 pub mod js_stream_service;
+pub mod nats_health;
 pub mod nats_js_client;
+pub mod nats_quota;
 pub mod nats_server;
 pub mod nats_types;
@@ -0,0 +1,256 @@
+/* --------
+This file contains the standardized health-check responder that every microservice registers for each
+of its JetStream services, and a helper to collect the resulting reports across the fleet.
+
+Subjects:
+- `HEALTH.<service>.<instance>`: replies with the health of a single service instance
+- `HEALTH.<service>`: every instance of the service replies
+- `HEALTH.all`: every instance of every service replies
+-------- */
+
+use super::js_stream_service::JsStreamService;
+use anyhow::Result;
+use async_nats::Client;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const HEALTH_SRV_SUBJ: &str = "HEALTH";
+pub const HEALTH_ALL_SUBJ: &str = "HEALTH.all";
+
+pub type DependencyCheck =
+    Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<(), anyhow::Error>> + Send>> + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DependencyStatus {
+    Up,
+    Down(String), // String = error message
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsumerLag {
+    pub consumer: String,
+    pub num_pending: u64,
+    pub num_ack_pending: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub service: String,
+    pub instance: String,
+    pub version: String,
+    pub uptime_secs: u64,
+    pub consumer_lag: Vec<ConsumerLag>,
+    pub dependencies: HashMap<String, DependencyStatus>,
+}
+
+impl HealthReport {
+    pub fn is_healthy(&self) -> bool {
+        self.dependencies
+            .values()
+            .all(|status| *status == DependencyStatus::Up)
+    }
+}
+
+/// Subscribe to the health subjects of the given service and reply to each request with a fresh `HealthReport`
+pub async fn spawn_health_responder(
+    client: Client,
+    service: JsStreamService,
+    instance: &str,
+    dependencies: Vec<(String, DependencyCheck)>,
+) -> Result<(), async_nats::Error> {
+    let service_info = service.get_service_info();
+    let service_name = service_info.name.to_string();
+    let version = service_info.version.to_string();
+    let log_prefix = format!("HEALTH-LOG::{}::", service_name);
+
+    let mut subscriptions = vec![];
+    for subject in responder_subjects(&service_name, instance) {
+        subscriptions.push(client.subscribe(subject).await?);
+    }
+    let mut requests = futures::stream::select_all(subscriptions);
+
+    let started_at = Instant::now();
+    let instance = instance.to_string();
+
+    tokio::spawn(async move {
+        while let Some(msg) = requests.next().await {
+            let reply = match msg.reply {
+                Some(reply) => reply,
+                None => continue,
+            };
+
+            let report = HealthReport {
+                service: service_name.clone(),
+                instance: instance.clone(),
+                version: version.clone(),
+                uptime_secs: started_at.elapsed().as_secs(),
+                consumer_lag: get_consumer_lag(&service).await,
+                dependencies: check_dependencies(&dependencies).await,
+            };
+
+            let response_bytes: bytes::Bytes = match serde_json::to_vec(&report) {
+                Ok(r) => r.into(),
+                Err(e) => e.to_string().into(),
+            };
+
+            if let Err(err) = client.publish(reply, response_bytes).await {
+                log::error!(
+                    "{}Failed to reply to health check: instance={}, err={:?}",
+                    log_prefix,
+                    instance,
+                    err
+                );
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Request health reports from every instance of `service` (or of every service when `None`),
+/// collecting all replies that arrive before the timeout elapses
+pub async fn collect_health_reports(
+    client: &Client,
+    service: Option<&str>,
+    timeout: Duration,
+) -> Result<Vec<HealthReport>, async_nats::Error> {
+    let subject = request_subject(service);
+    let inbox = client.new_inbox();
+    let mut replies = client.subscribe(inbox.clone()).await?;
+    client
+        .publish_with_reply(subject, inbox, bytes::Bytes::new())
+        .await?;
+    client.flush().await?;
+
+    let mut reports = vec![];
+    let deadline = tokio::time::sleep(timeout);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            maybe_msg = replies.next() => match maybe_msg {
+                Some(msg) => reports.extend(parse_report(&msg.payload)),
+                None => break,
+            },
+            _ = &mut deadline => break,
+        }
+    }
+
+    replies.unsubscribe().await?;
+    Ok(reports)
+}
+
+// Subjects a service instance answers health requests on
+fn responder_subjects(service_name: &str, instance: &str) -> Vec<String> {
+    vec![
+        format!("{}.{}.{}", HEALTH_SRV_SUBJ, service_name, instance),
+        format!("{}.{}", HEALTH_SRV_SUBJ, service_name),
+        HEALTH_ALL_SUBJ.to_string(),
+    ]
+}
+
+// Subject reaching every instance of the service (or of every service when `None`)
+fn request_subject(service: Option<&str>) -> String {
+    match service {
+        Some(service_name) => format!("{}.{}", HEALTH_SRV_SUBJ, service_name),
+        None => HEALTH_ALL_SUBJ.to_string(),
+    }
+}
+
+// NB: Malformed replies are logged and skipped, so that one faulty instance does not hide the reports of the others
+fn parse_report(payload: &[u8]) -> Option<HealthReport> {
+    match serde_json::from_slice::<HealthReport>(payload) {
+        Ok(report) => Some(report),
+        Err(e) => {
+            log::warn!("Received malformed health report. Err={:?}", e);
+            None
+        }
+    }
+}
+
+async fn get_consumer_lag(service: &JsStreamService) -> Vec<ConsumerLag> {
+    match service.get_consumers_info().await {
+        Ok(infos) => infos
+            .into_iter()
+            .map(|info| ConsumerLag {
+                consumer: info.name,
+                num_pending: info.num_pending,
+                num_ack_pending: info.num_ack_pending,
+            })
+            .collect(),
+        Err(e) => {
+            log::warn!(
+                "Failed to fetch consumer info for health check. Err={:?}",
+                e
+            );
+            vec![]
+        }
+    }
+}
+
+async fn check_dependencies(
+    dependencies: &[(String, DependencyCheck)],
+) -> HashMap<String, DependencyStatus> {
+    let mut statuses = HashMap::new();
+    for (name, check) in dependencies.iter() {
+        let status = match check().await {
+            Ok(()) => DependencyStatus::Up,
+            Err(e) => DependencyStatus::Down(e.to_string()),
+        };
+        statuses.insert(name.to_owned(), status);
+    }
+    statuses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_subjects() {
+        assert_eq!(
+            responder_subjects("WORKLOAD", "host_1"),
+            vec!["HEALTH.WORKLOAD.host_1", "HEALTH.WORKLOAD", "HEALTH.all"]
+        );
+        assert_eq!(request_subject(Some("WORKLOAD")), "HEALTH.WORKLOAD");
+        assert_eq!(request_subject(None), "HEALTH.all");
+    }
+
+    #[tokio::test]
+    async fn test_report_aggregation() {
+        let up: DependencyCheck = Arc::new(|| Box::pin(async { Ok(()) }));
+        let down: DependencyCheck =
+            Arc::new(|| Box::pin(async { Err(anyhow::anyhow!("connection refused")) }));
+
+        let mut report = HealthReport {
+            service: "WORKLOAD".to_string(),
+            instance: "host_1".to_string(),
+            version: "0.0.1".to_string(),
+            uptime_secs: 42,
+            consumer_lag: vec![],
+            dependencies: check_dependencies(&[("nats".to_string(), up.clone())]).await,
+        };
+        assert!(report.is_healthy());
+
+        report.dependencies =
+            check_dependencies(&[("nats".to_string(), up), ("mongodb".to_string(), down)]).await;
+        assert_eq!(report.dependencies["nats"], DependencyStatus::Up);
+        assert_eq!(
+            report.dependencies["mongodb"],
+            DependencyStatus::Down("connection refused".to_string())
+        );
+        assert!(!report.is_healthy());
+
+        // replies are collected as long as they are well-formed
+        let payload = serde_json::to_vec(&report).unwrap();
+        let parsed = parse_report(&payload).unwrap();
+        assert_eq!(parsed.instance, "host_1");
+        assert_eq!(parsed.dependencies, report.dependencies);
+        assert!(parse_report(b"not a report").is_none());
+    }
+}
@@ -1,9 +1,10 @@
 use super::js_stream_service::{CreateTag, JsServiceParamsPartial, JsStreamService};
+use super::nats_health::{self, DependencyCheck, HealthReport};
 use crate::nats_server::LEAF_SERVER_DEFAULT_LISTEN_PORT;
 
 use anyhow::Result;
 use async_nats::{jetstream, Message, ServerInfo};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::error::Error;
 use std::fmt;
 use std::fmt::Debug;
@@ -61,6 +62,15 @@ impl fmt::Display for ErrClientDisconnected {
 }
 impl Error for ErrClientDisconnected {}
 
+#[derive(Debug)]
+pub struct ErrRequestCancelled;
+impl fmt::Display for ErrRequestCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Request cancelled before a reply was received")
+    }
+}
+impl Error for ErrRequestCancelled {}
+
 impl std::fmt::Debug for JsClient {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("JsClient")
@@ -83,6 +93,8 @@ pub struct JsClient {
     pub js: jetstream::Context,
     pub js_services: Option<Vec<JsStreamService>>,
     service_log_prefix: String,
+    #[cfg(feature = "fault_injection")]
+    faults: crate::fault_injection::FaultInjector,
 }
 
 #[derive(Deserialize, Default)]
@@ -100,6 +112,8 @@ pub struct NewJsClientParams {
     pub ping_interval: Option<Duration>,
     #[serde(default)]
     pub request_timeout: Option<Duration>, // Defaults to 5s
+    #[serde(default)]
+    pub js_domain: Option<String>, // JetStream domain the client's streams live in. Defaults to the domain of the connected server
 }
 
 impl JsClient {
@@ -123,7 +137,10 @@ impl JsClient {
             None => connect_options.connect(&p.nats_url).await?,
         };
 
-        let jetstream = jetstream::new(client.clone());
+        let jetstream = match p.js_domain {
+            Some(domain) => jetstream::with_domain(client.clone(), domain),
+            None => jetstream::new(client.clone()),
+        };
         let mut services = vec![];
         for params in p.service_params {
             let service = JsStreamService::new(
@@ -154,6 +171,8 @@ impl JsClient {
             js: jetstream,
             js_services,
             service_log_prefix: service_log_prefix.clone(),
+            #[cfg(feature = "fault_injection")]
+            faults: Default::default(),
         };
 
         for opt in p.opts {
@@ -168,6 +187,20 @@ impl JsClient {
         Ok(default_client)
     }
 
+    /// Inject the faults into the messages published by this client, and into its js services
+    // NB: This is meant to be called before the consumer handlers of the js services are spawned
+    #[cfg(feature = "fault_injection")]
+    pub fn with_faults(mut self, faults: crate::fault_injection::FaultInjector) -> Self {
+        self.js_services = self.js_services.map(|services| {
+            services
+                .into_iter()
+                .map(|service| service.with_faults(faults.clone()))
+                .collect()
+        });
+        self.faults = faults;
+        self
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -204,16 +237,124 @@ impl JsClient {
         Ok(())
     }
 
+    /// Register the standardized `HEALTH.<service>.<instance>` responder for every js service on this client
+    pub async fn add_health_responders(
+        &self,
+        instance: &str,
+        dependencies: Vec<(String, DependencyCheck)>,
+    ) -> Result<(), async_nats::Error> {
+        for service in self.js_services.iter().flatten() {
+            nats_health::spawn_health_responder(
+                self.client.clone(),
+                service.clone(),
+                instance,
+                dependencies.clone(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_health_reports(
+        &self,
+        service_name: Option<&str>,
+        timeout: Duration,
+    ) -> Result<Vec<HealthReport>, async_nats::Error> {
+        nats_health::collect_health_reports(&self.client, service_name, timeout).await
+    }
+
     pub async fn request(&self, _payload: &SendRequest) -> Result<(), async_nats::Error> {
         Ok(())
     }
 
+    /// Send a request and wait for its reply, giving up after `timeout` or as soon as ctrl-c is received.
+    /// NB: This is intended for CLI tools, so that each command doesn't need to hand-roll its own subscribe/spawn/ctrl_c logic.
+    pub async fn rpc(
+        &self,
+        subject: &str,
+        payload: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<Message, async_nats::Error> {
+        self.rpc_with_cancellation(subject, payload, timeout, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+    }
+
+    /// Send a request and wait for its reply, giving up after `timeout` or as soon as the `cancel` future resolves
+    pub async fn rpc_with_cancellation<C>(
+        &self,
+        subject: &str,
+        payload: Vec<u8>,
+        timeout: Duration,
+        cancel: C,
+    ) -> Result<Message, async_nats::Error>
+    where
+        C: Future<Output = ()>,
+    {
+        let request = async_nats::Request::new()
+            .payload(payload.into())
+            .timeout(Some(timeout));
+
+        tokio::select! {
+            reply = self.client.send_request(subject.to_string(), request) => {
+                let reply = reply?;
+                log::debug!(
+                    "{}Received reply: subj={}, data={:?}",
+                    self.service_log_prefix,
+                    subject,
+                    reply.payload
+                );
+                Ok(reply)
+            }
+            _ = cancel => {
+                log::debug!(
+                    "{}Cancelled request: subj={}",
+                    self.service_log_prefix,
+                    subject
+                );
+                Err(Box::new(ErrRequestCancelled))
+            }
+        }
+    }
+
+    /// Same as `rpc`, but serializes the request and deserializes the reply as json
+    pub async fn rpc_json<Req, Res>(
+        &self,
+        subject: &str,
+        payload: &Req,
+        timeout: Duration,
+    ) -> Result<Res, async_nats::Error>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned,
+    {
+        let payload = serde_json::to_vec(payload)?;
+        let reply = self.rpc(subject, payload, timeout).await?;
+        Ok(serde_json::from_slice(&reply.payload)?)
+    }
+
     pub async fn publish(&self, payload: &SendRequest) -> Result<(), async_nats::Error> {
+        #[cfg(feature = "fault_injection")]
+        if self.faults.should_drop_publish() {
+            log::warn!(
+                "{}Injected fault: dropped message: subj={}, msg_id={}",
+                self.service_log_prefix,
+                payload.subject,
+                payload.msg_id
+            );
+            return Ok(());
+        }
+
+        // NB: The msg_id is sent as the `Nats-Msg-Id` header, so that JetStream drops a message published twice
+        // (eg: when a publish is retried) within the duplicate window of the stream
+        let mut publish = jetstream::context::Publish::build().payload(payload.data.clone().into());
+        if !payload.msg_id.is_empty() {
+            publish = publish.message_id(payload.msg_id.clone());
+        }
+
         let now = Instant::now();
-        let result = self
-            .js
-            .publish(payload.subject.clone(), payload.data.clone().into())
-            .await;
+        let result = self.js.send_publish(payload.subject.clone(), publish).await;
 
         let duration = now.elapsed();
         if let Err(err) = result {
@@ -335,6 +476,7 @@ mod tests {
             credentials_path: None,
             ping_interval: Some(Duration::from_secs(10)),
             request_timeout: Some(Duration::from_secs(5)),
+            js_domain: None,
             opts: vec![],
         }
     }
@@ -359,7 +501,47 @@ mod tests {
             data: b"Hello, NATS!".to_vec(),
         };
 
-        let result = client.publish(&publish_options).await;
+        let result = client.publish(&payload).await;
         assert!(result.is_ok(), "Publishing message failed: {:?}", result);
     }
+
+    #[cfg(feature = "fault_injection")]
+    #[tokio::test]
+    async fn test_nats_js_client_publish_with_faults() {
+        use crate::fault_injection::{FaultConfig, FaultInjector};
+
+        let faults = FaultInjector::new(FaultConfig {
+            publish_drop_rate: 1.0,
+            ..Default::default()
+        });
+        let mut params = get_default_params();
+        params.service_params = vec![JsServiceParamsPartial {
+            name: "FAULTS_TEST".to_string(),
+            description: "Fault injection test".to_string(),
+            version: "0.0.1".to_string(),
+            service_subject: "FAULTS_TEST".to_string(),
+        }];
+        let client = JsClient::new(params)
+            .await
+            .unwrap()
+            .with_faults(faults.clone());
+        let mut stream = client.js.get_stream("FAULTS_TEST").await.unwrap();
+        stream.purge().await.unwrap();
+
+        let payload = SendRequest {
+            subject: "FAULTS_TEST.command".to_string(),
+            msg_id: "test_faults_msg".to_string(),
+            data: b"Hello, NATS!".to_vec(),
+        };
+
+        // the dropped message still reports success, but never reaches the stream
+        assert!(client.publish(&payload).await.is_ok());
+        assert_eq!(stream.info().await.unwrap().state.messages, 0);
+
+        // a retried publish of the same message is dropped by JetStream as a duplicate
+        faults.clear();
+        client.publish(&payload).await.unwrap();
+        client.publish(&payload).await.unwrap();
+        assert_eq!(stream.info().await.unwrap().state.messages, 1);
+    }
 }
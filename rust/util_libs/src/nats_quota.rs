@@ -0,0 +1,294 @@
+/* --------
+This file contains a publisher wrapper that enforces a publishing budget per service, so that a runaway
+service (eg: a buggy metrics loop) cannot exhaust the leafnode link bandwidth other services depend on.
+
+Each service is given a message-rate and a byte-rate budget (token buckets allowing bursts of up to one
+second worth of traffic) and its own bounded queue. A single dispatcher drains the queues in round-robin
+order, skipping services that are over budget, so every service gets a fair share of the link.
+-------- */
+
+use super::nats_js_client::{JsClient, SendRequest};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Notify};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublishBudget {
+    pub max_msgs_per_sec: u32,
+    pub max_bytes_per_sec: u64,
+}
+
+#[derive(Debug)]
+pub struct ErrQuotaExceeded(pub String);
+impl fmt::Display for ErrQuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Publish quota exceeded: {}", self.0)
+    }
+}
+impl Error for ErrQuotaExceeded {}
+
+type PublishResult = Result<(), async_nats::Error>;
+
+#[derive(Debug)]
+struct TokenBucket {
+    msgs: f64,
+    bytes: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(budget: &PublishBudget) -> Self {
+        Self {
+            msgs: budget.max_msgs_per_sec as f64,
+            bytes: budget.max_bytes_per_sec as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, budget: &PublishBudget, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.msgs = (self.msgs + elapsed * budget.max_msgs_per_sec as f64)
+            .min(budget.max_msgs_per_sec as f64);
+        self.bytes = (self.bytes + elapsed * budget.max_bytes_per_sec as f64)
+            .min(budget.max_bytes_per_sec as f64);
+        self.last_refill = now;
+    }
+
+    fn try_take(&mut self, bytes: usize) -> bool {
+        if self.msgs >= 1.0 && self.bytes >= bytes as f64 {
+            self.msgs -= 1.0;
+            self.bytes -= bytes as f64;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Time until enough tokens are available to publish a message of `bytes` bytes
+    fn wait_time(&self, budget: &PublishBudget, bytes: usize) -> Duration {
+        let msgs_wait = (1.0 - self.msgs).max(0.0) / budget.max_msgs_per_sec as f64;
+        let bytes_wait = (bytes as f64 - self.bytes).max(0.0) / budget.max_bytes_per_sec as f64;
+        Duration::from_secs_f64(msgs_wait.max(bytes_wait))
+    }
+}
+
+#[derive(Debug)]
+struct ServiceQueue {
+    budget: PublishBudget,
+    bucket: TokenBucket,
+    pending: VecDeque<(SendRequest, oneshot::Sender<PublishResult>)>,
+}
+
+#[derive(Debug, Default)]
+struct QuotaState {
+    queues: HashMap<String, ServiceQueue>,
+    last_served: Option<String>,
+}
+
+impl QuotaState {
+    // Pop the next message to publish, visiting the services in round-robin order.
+    // Returns the time to wait for a budget refill when messages are pending but no service is within budget.
+    fn next_ready(
+        &mut self,
+        now: Instant,
+    ) -> Result<(SendRequest, oneshot::Sender<PublishResult>), Option<Duration>> {
+        let mut services: Vec<String> = self.queues.keys().cloned().collect();
+        services.sort();
+        if let Some(last_served) = &self.last_served {
+            let start = services.partition_point(|service| service <= last_served);
+            services.rotate_left(start);
+        }
+
+        let mut min_wait: Option<Duration> = None;
+        for service in services {
+            let queue = self.queues.get_mut(&service).expect("service queue exists");
+            let msg_len = match queue.pending.front() {
+                Some((request, _)) => request.data.len(),
+                None => continue,
+            };
+
+            queue.bucket.refill(&queue.budget, now);
+            if queue.bucket.try_take(msg_len) {
+                let next = queue.pending.pop_front().expect("pending message exists");
+                self.last_served = Some(service);
+                return Ok(next);
+            }
+
+            let wait = queue.bucket.wait_time(&queue.budget, msg_len);
+            min_wait = Some(min_wait.map_or(wait, |min| min.min(wait)));
+        }
+        Err(min_wait)
+    }
+}
+
+pub struct QuotaPublisher {
+    state: Arc<Mutex<QuotaState>>,
+    notify: Arc<Notify>,
+    queue_capacity: usize,
+    dispatcher: JoinHandle<()>,
+}
+
+impl QuotaPublisher {
+    /// Wrap `client` with the given per-service budgets.
+    /// Each service can queue up to `queue_capacity` messages before publishing is rejected.
+    pub fn new(
+        client: Arc<JsClient>,
+        budgets: HashMap<String, PublishBudget>,
+        queue_capacity: usize,
+    ) -> Self {
+        let queues = budgets
+            .into_iter()
+            .map(|(service, budget)| {
+                let queue = ServiceQueue {
+                    bucket: TokenBucket::new(&budget),
+                    budget,
+                    pending: VecDeque::new(),
+                };
+                (service, queue)
+            })
+            .collect();
+
+        let state = Arc::new(Mutex::new(QuotaState {
+            queues,
+            last_served: None,
+        }));
+        let notify = Arc::new(Notify::new());
+        let dispatcher = tokio::spawn(Self::dispatch(client, state.clone(), notify.clone()));
+
+        Self {
+            state,
+            notify,
+            queue_capacity,
+            dispatcher,
+        }
+    }
+
+    /// Queue `payload` on behalf of `service` and wait until it has been published
+    pub async fn publish(&self, service: &str, payload: SendRequest) -> PublishResult {
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let queue = state.queues.get_mut(service).ok_or_else(|| {
+                ErrQuotaExceeded(format!("No publish budget defined for service={}", service))
+            })?;
+
+            if payload.data.len() as u64 > queue.budget.max_bytes_per_sec {
+                return Err(Box::new(ErrQuotaExceeded(format!(
+                    "Message larger than the byte budget of service={}: subj={}, size={}",
+                    service,
+                    payload.subject,
+                    payload.data.len()
+                ))));
+            }
+            if queue.pending.len() >= self.queue_capacity {
+                return Err(Box::new(ErrQuotaExceeded(format!(
+                    "Publish queue full for service={}: subj={}",
+                    service, payload.subject
+                ))));
+            }
+            queue.pending.push_back((payload, tx));
+        }
+        self.notify.notify_one();
+
+        rx.await?
+    }
+
+    async fn dispatch(client: Arc<JsClient>, state: Arc<Mutex<QuotaState>>, notify: Arc<Notify>) {
+        loop {
+            let next = state
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .next_ready(Instant::now());
+
+            match next {
+                Ok((payload, tx)) => {
+                    let result = client.publish(&payload).await;
+                    // NB: The caller may have stopped waiting for the result, which is fine.
+                    let _ = tx.send(result);
+                }
+                Err(Some(wait)) => {
+                    tokio::select! {
+                        _ = tokio::time::sleep(wait) => {},
+                        _ = notify.notified() => {},
+                    }
+                }
+                Err(None) => notify.notified().await,
+            }
+        }
+    }
+}
+
+impl Drop for QuotaPublisher {
+    fn drop(&mut self) {
+        self.dispatcher.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue_messages(state: &mut QuotaState, service: &str, count: usize) {
+        let queue = state.queues.get_mut(service).unwrap();
+        for i in 0..count {
+            let (tx, _) = oneshot::channel();
+            let request = SendRequest {
+                subject: format!("{}.subject", service),
+                msg_id: i.to_string(),
+                data: vec![0; 10],
+            };
+            queue.pending.push_back((request, tx));
+        }
+    }
+
+    fn new_state(budgets: Vec<(&str, PublishBudget)>) -> QuotaState {
+        let queues = budgets
+            .into_iter()
+            .map(|(service, budget)| {
+                let queue = ServiceQueue {
+                    bucket: TokenBucket::new(&budget),
+                    budget,
+                    pending: VecDeque::new(),
+                };
+                (service.to_string(), queue)
+            })
+            .collect();
+        QuotaState {
+            queues,
+            last_served: None,
+        }
+    }
+
+    #[test]
+    fn test_fair_queuing_and_budgets() {
+        let budget = PublishBudget {
+            max_msgs_per_sec: 2,
+            max_bytes_per_sec: 1000,
+        };
+        let mut state = new_state(vec![("METRICS", budget.clone()), ("WORKLOAD", budget)]);
+        queue_messages(&mut state, "METRICS", 5);
+        queue_messages(&mut state, "WORKLOAD", 1);
+        let now = Instant::now();
+
+        // The services are served in turn, even though METRICS queued more messages
+        let served: Vec<String> = (0..3)
+            .map(|_| state.next_ready(now).ok().unwrap().0.subject)
+            .collect();
+        assert_eq!(
+            served,
+            vec!["METRICS.subject", "WORKLOAD.subject", "METRICS.subject"]
+        );
+
+        // METRICS has used up its message budget and must wait for a refill
+        match state.next_ready(now) {
+            Err(Some(wait)) => assert!(wait > Duration::ZERO),
+            _ => panic!("METRICS should be over budget"),
+        }
+        let (request, _) = state.next_ready(now + Duration::from_secs(1)).ok().unwrap();
+        assert_eq!(request.subject, "METRICS.subject");
+    }
+}
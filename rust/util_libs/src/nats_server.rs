@@ -16,11 +16,13 @@ use tokio::sync::Mutex;
 pub const LEAF_SERVER_CONFIG_PATH: &str = "test_leaf_server.conf";
 pub const LEAF_SERVER_DEFAULT_LISTEN_PORT: u16 = 4111;
 
+#[skip_serializing_none]
 #[derive(Serialize, Debug, Clone)]
 pub struct JetStreamConfig {
     pub store_dir: PathBuf,
     pub max_memory_store: u64,
     pub max_file_store: u64,
+    pub domain: Option<String>, // JetStream domain of the leaf server, isolating its streams from the hub's
 }
 
 #[derive(Debug, Clone)]
@@ -305,6 +307,7 @@ mod tests {
             store_dir: format!("{}/leaf_store", TMP_JS_DIR),
             max_memory_store: 1024 * 1024 * 1024, // 1 GB
             max_file_store: 1024 * 1024 * 1024,   // 1 GB
+            domain: None,
         };
 
         let logging_options = LoggingOptions {